@@ -207,6 +207,59 @@ impl Color
     {
         self.r * 0.299 + self.g * 0.587 + self.b * 0.114
     }
+
+    /// Returns this color with the red, green, and blue components multiplied
+    /// by the alpha component, as expected by premultiplied alpha blending.
+    #[inline]
+    #[must_use]
+    pub fn premultiplied(&self) -> Self
+    {
+        Self::from_rgba(self.r * self.a, self.g * self.a, self.b * self.a, self.a)
+    }
+
+    /// Converts this color from sRGB to linear light, leaving alpha as it is.
+    /// Colors passed to the drawing functions are always sRGB, but blending
+    /// and interpolation are only physically correct in linear light.
+    #[must_use]
+    pub fn to_linear(&self) -> Self
+    {
+        let convert = |component: f32| {
+            if component <= 0.04045 {
+                component / 12.92
+            } else {
+                ((component + 0.055) / 1.055).powf(2.4)
+            }
+        };
+
+        Self::from_rgba(convert(self.r), convert(self.g), convert(self.b), self.a)
+    }
+
+    /// Returns the result of drawing this color over the top of `background`,
+    /// using the standard "source over" blending for colors which are not
+    /// premultiplied by alpha.
+    ///
+    /// If both colors are fully transparent, the result is
+    /// [Color::TRANSPARENT].
+    #[must_use]
+    pub fn over(&self, background: &Color) -> Self
+    {
+        let a = self.a + background.a * (1.0 - self.a);
+
+        if a <= 0.0 {
+            return Color::TRANSPARENT;
+        }
+
+        let blend = |source: f32, destination: f32| {
+            (source * self.a + destination * background.a * (1.0 - self.a)) / a
+        };
+
+        Self::from_rgba(
+            blend(self.r, background.r),
+            blend(self.g, background.g),
+            blend(self.b, background.b),
+            a
+        )
+    }
 }
 
 impl From<[u8; 4]> for Color
@@ -256,4 +309,41 @@ mod tests
             Color::from_int_rgba(0xFF, 0x55, 0x11, 0xAA)
         );
     }
+
+    #[test]
+    fn test_premultiplied()
+    {
+        assert_eq!(
+            Color::from_rgba(1.0, 0.5, 0.25, 0.5).premultiplied(),
+            Color::from_rgba(0.5, 0.25, 0.125, 0.5)
+        );
+
+        assert_eq!(Color::WHITE.premultiplied(), Color::WHITE);
+    }
+
+    #[test]
+    fn test_to_linear()
+    {
+        assert_eq!(Color::BLACK.to_linear(), Color::BLACK);
+        assert_eq!(Color::WHITE.to_linear(), Color::WHITE);
+
+        let grey = Color::from_rgba(0.5, 0.5, 0.5, 0.5).to_linear();
+        assert!((grey.r() - 0.214).abs() < 0.001);
+        assert_eq!(grey.a(), 0.5);
+    }
+
+    #[test]
+    fn test_over()
+    {
+        let red = Color::from_rgba(1.0, 0.0, 0.0, 0.5);
+
+        assert_eq!(red.over(&Color::BLUE), Color::from_rgba(0.5, 0.0, 0.5, 1.0));
+        assert_eq!(red.over(&Color::TRANSPARENT), red);
+        assert_eq!(Color::TRANSPARENT.over(&red), red);
+        assert_eq!(Color::GREEN.over(&red), Color::GREEN);
+        assert_eq!(
+            Color::TRANSPARENT.over(&Color::TRANSPARENT),
+            Color::TRANSPARENT
+        );
+    }
 }
@@ -18,7 +18,7 @@ use std::convert::TryInto;
 
 use num_traits::{AsPrimitive, Zero};
 
-use crate::numeric::{PrimitiveZero, RoundFloat};
+use crate::numeric::{max, min, PrimitiveZero, RoundFloat};
 
 /// A vector with two f32 values.
 pub type Vec2 = Vector2<f32>;
@@ -143,6 +143,35 @@ impl<T: std::ops::Neg<Output = T> + Copy> Vector2<T>
     }
 }
 
+impl<T: PartialOrd + Copy> Vector2<T>
+{
+    /// Returns the smaller of each component of `self` and `other`.
+    #[inline]
+    #[must_use]
+    pub fn min(&self, other: &Self) -> Vector2<T>
+    {
+        Vector2::new(min(self.x, other.x), min(self.y, other.y))
+    }
+
+    /// Returns the larger of each component of `self` and `other`.
+    #[inline]
+    #[must_use]
+    pub fn max(&self, other: &Self) -> Vector2<T>
+    {
+        Vector2::new(max(self.x, other.x), max(self.y, other.y))
+    }
+
+    /// Restricts each component to the range between the same components of
+    /// `min` and `max`, for example to keep a dragged object within bounds.
+    /// Each component of `min` should be no greater than that of `max`.
+    #[inline]
+    #[must_use]
+    pub fn clamp(&self, min: &Self, max: &Self) -> Vector2<T>
+    {
+        self.max(min).min(max)
+    }
+}
+
 impl<T: num_traits::AsPrimitive<f32>> Vector2<T>
 {
     /// Returns a new vector with each element cast to `f32`, using the `as`
@@ -512,6 +541,21 @@ mod test
 
         assert_eq!(Vector2::new(5, -12), &Vector2::new(10, 4) - &(5, 16));
     }
+
+    #[test]
+    fn test_min_max_clamp()
+    {
+        let a = Vector2::new(3, -4);
+        let b = Vector2::new(-1, 6);
+
+        assert_eq!(a.min(&b), Vector2::new(-1, -4));
+        assert_eq!(a.max(&b), Vector2::new(3, 6));
+
+        let (low, high) = (Vector2::new(0.0, 0.0), Vector2::new(10.0, 5.0));
+        assert_eq!(Vec2::new(-2.0, 3.0).clamp(&low, &high), Vec2::new(0.0, 3.0));
+        assert_eq!(Vec2::new(12.0, 7.5).clamp(&low, &high), Vec2::new(10.0, 5.0));
+    }
+
     #[test]
     fn test_add_assign()
     {
@@ -0,0 +1,211 @@
+/*
+ *  Copyright 2021 QuantumBadger
+ *
+ *  Licensed under the Apache License, Version 2.0 (the "License");
+ *  you may not use this file except in compliance with the License.
+ *  You may obtain a copy of the License at
+ *
+ *      http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *  Unless required by applicable law or agreed to in writing, software
+ *  distributed under the License is distributed on an "AS IS" BASIS,
+ *  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ *  See the License for the specific language governing permissions and
+ *  limitations under the License.
+ */
+
+use std::cell::Cell;
+
+use crate::dimen::{UVec2, Vec2};
+
+thread_local! {
+    static CONTENT_SCALE: Cell<f32> = const { Cell::new(1.0) };
+}
+
+/// The user-controlled zoom applied on top of the display's own scale
+/// factor. See [crate::window::WindowHelper::set_content_scale].
+pub(crate) fn content_scale() -> f32
+{
+    CONTENT_SCALE.with(Cell::get)
+}
+
+pub(crate) fn set_content_scale(scale: f32)
+{
+    CONTENT_SCALE.with(|content_scale| content_scale.set(scale));
+}
+
+/// Converts between the physical pixels reported by miniquad and the logical
+/// (DPI-scaled) pixels used throughout the public API.
+///
+/// Every conversion between the two goes through this type, so that positions
+/// and sizes round the same way wherever they are converted.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct DpiScaler
+{
+    scale: f32
+}
+
+impl DpiScaler
+{
+    #[inline]
+    #[must_use]
+    pub(crate) fn new(scale: f32) -> Self
+    {
+        DpiScaler { scale }
+    }
+
+    /// Uses the current scale factor of the window, multiplied by the
+    /// content scale, so that logical pixels grow with both.
+    #[inline]
+    #[must_use]
+    pub(crate) fn current() -> Self
+    {
+        Self::new(miniquad::window::dpi_scale() * content_scale())
+    }
+
+    #[inline]
+    #[must_use]
+    pub(crate) fn scale(self) -> f32
+    {
+        self.scale
+    }
+
+    #[inline]
+    #[must_use]
+    pub(crate) fn to_logical(self, physical: f32) -> f32
+    {
+        physical / self.scale
+    }
+
+    #[inline]
+    #[must_use]
+    pub(crate) fn to_physical(self, logical: f32) -> f32
+    {
+        logical * self.scale
+    }
+
+    #[inline]
+    #[must_use]
+    pub(crate) fn to_logical_point(self, x: f32, y: f32) -> Vec2
+    {
+        Vec2::new(self.to_logical(x), self.to_logical(y))
+    }
+
+    /// Converts a position to the nearest physical pixel.
+    #[inline]
+    #[must_use]
+    pub(crate) fn to_physical_point(self, position: Vec2) -> (i32, i32)
+    {
+        (
+            self.to_physical(position.x).round() as i32,
+            self.to_physical(position.y).round() as i32
+        )
+    }
+
+    /// Converts a physical size to the whole number of logical pixels which
+    /// fit inside it.
+    #[inline]
+    #[must_use]
+    pub(crate) fn to_logical_size(self, width: f32, height: f32) -> UVec2
+    {
+        UVec2::new(
+            self.to_logical(width) as u32,
+            self.to_logical(height) as u32
+        )
+    }
+
+    /// The size at which to rasterize a font so that it stays sharp, rounded
+    /// up to a whole pixel size.
+    #[inline]
+    #[must_use]
+    pub(crate) fn to_physical_font_size(self, font_size: u16) -> u16
+    {
+        self.to_physical(font_size as f32).ceil() as u16
+    }
+
+    /// Moves a logical position to the nearest physical pixel boundary.
+    /// Glyphs drawn from such a position line up with the physical pixel grid
+    /// rather than being sampled half way between pixels.
+    #[inline]
+    #[must_use]
+    pub(crate) fn snap_to_physical(self, position: glam::Vec2) -> glam::Vec2
+    {
+        glam::vec2(
+            self.to_logical(self.to_physical(position.x).round()),
+            self.to_logical(self.to_physical(position.y).round())
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    const SCALES: [f32; 3] = [1.0, 1.5, 2.0];
+
+    #[test]
+    fn test_round_trip()
+    {
+        for &scale in &SCALES {
+            let dpi = DpiScaler::new(scale);
+            for &value in &[0.0, 1.0, 7.5, 333.25] {
+                assert!((dpi.to_logical(dpi.to_physical(value)) - value).abs() < 1e-4);
+                assert!((dpi.to_physical(dpi.to_logical(value)) - value).abs() < 1e-4);
+            }
+        }
+    }
+
+    #[test]
+    fn test_sizes()
+    {
+        let expected = [(800, 600), (533, 400), (400, 300)];
+
+        for (&scale, &(width, height)) in SCALES.iter().zip(&expected) {
+            let dpi = DpiScaler::new(scale);
+            assert_eq!(dpi.to_logical_size(800.0, 600.0), UVec2::new(width, height));
+        }
+
+        assert_eq!(DpiScaler::new(1.0).to_physical_font_size(15), 15);
+        assert_eq!(DpiScaler::new(1.5).to_physical_font_size(15), 23);
+        assert_eq!(DpiScaler::new(2.0).to_physical_font_size(15), 30);
+    }
+
+    #[test]
+    fn test_points()
+    {
+        assert_eq!(
+            DpiScaler::new(1.0).to_physical_point(Vec2::new(10.4, 10.6)),
+            (10, 11)
+        );
+        assert_eq!(
+            DpiScaler::new(1.5).to_physical_point(Vec2::new(10.4, 10.6)),
+            (16, 16)
+        );
+        assert_eq!(
+            DpiScaler::new(2.0).to_physical_point(Vec2::new(10.4, 10.6)),
+            (21, 21)
+        );
+
+        assert_eq!(
+            DpiScaler::new(2.0).to_logical_point(30.0, 15.0),
+            Vec2::new(15.0, 7.5)
+        );
+    }
+
+    #[test]
+    fn test_snap_to_physical()
+    {
+        for &scale in &SCALES {
+            let dpi = DpiScaler::new(scale);
+            for &value in &[0.0, 0.3, 10.5, 10.7, 101.1] {
+                let snapped = dpi.snap_to_physical(glam::vec2(value, value));
+                let physical = dpi.to_physical(snapped.x);
+
+                assert!((physical - physical.round()).abs() < 1e-4, "scale {}", scale);
+                assert!((snapped.x - value).abs() <= 0.5 / scale + 1e-4);
+                assert_eq!(snapped.x, snapped.y);
+            }
+        }
+    }
+}
@@ -14,7 +14,13 @@
  *  limitations under the License.
  */
 
+use std::cell::{Cell, RefCell};
+use std::hash::{Hash, Hasher};
+use std::rc::{Rc, Weak};
+
 use crate::dimen::UVec2;
+use crate::error::{BacktraceError, ErrorMessage};
+pub use crate::texture::Image;
 //use crate::glwrapper::GLTexture;
 
 /// The data type of the pixels making up the raw image data.
@@ -30,15 +36,86 @@ pub enum ImageDataType
     RGBA
 }
 
+impl ImageDataType
+{
+    pub(crate) fn bytes_per_pixel(self) -> usize
+    {
+        match self {
+            ImageDataType::RGB => 3,
+            ImageDataType::RGBA => 4
+        }
+    }
+}
+
+/// How the alpha channel of captured pixels is treated.
+#[derive(Debug, Hash, PartialEq, Eq, Clone, Copy)]
+pub enum CaptureAlpha
+{
+    /// Every pixel is made fully opaque. The alpha left in the window's
+    /// framebuffer is rarely meaningful, as the window is shown opaque
+    /// whatever it contains, so this suits screenshots.
+    Opaque,
+
+    /// Alpha is kept as it was drawn, for example to export a render target
+    /// with a transparent background as a PNG.
+    Preserve
+}
+
+impl CaptureAlpha
+{
+    pub(crate) fn apply(self, rgba: &mut [u8])
+    {
+        if self == CaptureAlpha::Opaque {
+            for pixel in rgba.chunks_exact_mut(4) {
+                pixel[3] = 255;
+            }
+        }
+    }
+}
+
+/// Converts raw pixel data of the given type into RGBA, checking that there is
+/// exactly enough data for an image of the given size.
+pub(crate) fn to_rgba8(
+    data_type: ImageDataType,
+    size: UVec2,
+    data: &[u8]
+) -> Result<Vec<u8>, BacktraceError<ErrorMessage>>
+{
+    let expected_len = size.x as usize * size.y as usize * data_type.bytes_per_pixel();
+
+    if data.len() != expected_len {
+        return Err(ErrorMessage::msg(format!(
+            "Expected {} bytes of {:?} data for a {}x{} image, got {}",
+            expected_len,
+            data_type,
+            size.x,
+            size.y,
+            data.len()
+        )));
+    }
+
+    Ok(match data_type {
+        ImageDataType::RGBA => data.to_vec(),
+        ImageDataType::RGB => data
+            .chunks_exact(3)
+            .flat_map(|rgb| [rgb[0], rgb[1], rgb[2], 255])
+            .collect()
+    })
+}
+
 /// Represents a handle for a loaded image.
 ///
+/// The image is deleted once every clone of its handle has been dropped, or
+/// when it is passed to
+/// [Graphics2D::delete_image](crate::Graphics2D::delete_image).
+///
 /// Note: this handle can only be used in the graphics context in which it was
 /// created.
-#[derive(Debug, Hash, PartialEq, Eq, Clone)]
+#[derive(Debug, Clone)]
 pub struct ImageHandle
 {
     pub(crate) size: UVec2,
-    pub(crate) texture: miniquad::TextureId
+    pub(crate) texture: Rc<ImageTexture>
 }
 
 impl ImageHandle
@@ -48,6 +125,98 @@ impl ImageHandle
     {
         &self.size
     }
+
+    /// The image's texture, or `None` once the image has been deleted.
+    pub(crate) fn texture(&self) -> Option<miniquad::TextureId>
+    {
+        self.texture.id()
+    }
+}
+
+impl PartialEq for ImageHandle
+{
+    fn eq(&self, other: &Self) -> bool
+    {
+        self.size == other.size && self.texture.id == other.texture.id
+    }
+}
+
+impl Eq for ImageHandle {}
+
+impl Hash for ImageHandle
+{
+    fn hash<H: Hasher>(&self, state: &mut H)
+    {
+        self.size.hash(state);
+        self.texture.id.hash(state);
+    }
+}
+
+/// Textures queued for deletion at the end of the frame.
+pub(crate) type DeletionQueue = Rc<RefCell<Vec<miniquad::TextureId>>>;
+
+/// The texture shared by every clone of an [ImageHandle].
+#[derive(Debug)]
+pub(crate) struct ImageTexture
+{
+    id: miniquad::TextureId,
+    deleted: Cell<bool>,
+    // None for textures owned by something else, such as a render target
+    deletion_queue: Option<Weak<RefCell<Vec<miniquad::TextureId>>>>
+}
+
+impl ImageTexture
+{
+    /// A texture which is deleted along with its last handle.
+    pub(crate) fn owned(id: miniquad::TextureId, deletion_queue: &DeletionQueue) -> Rc<Self>
+    {
+        Rc::new(ImageTexture {
+            id,
+            deleted: Cell::new(false),
+            deletion_queue: Some(Rc::downgrade(deletion_queue))
+        })
+    }
+
+    /// A texture which stays alive after its handles, deleted by its owner.
+    pub(crate) fn borrowed(id: miniquad::TextureId) -> Rc<Self>
+    {
+        Rc::new(ImageTexture {
+            id,
+            deleted: Cell::new(false),
+            deletion_queue: None
+        })
+    }
+
+    pub(crate) fn id(&self) -> Option<miniquad::TextureId>
+    {
+        if self.deleted.get() {
+            None
+        } else {
+            Some(self.id)
+        }
+    }
+
+    /// Queues the texture for deletion, unless it already has been, and
+    /// stops it being drawn.
+    pub(crate) fn delete(&self)
+    {
+        if self.deleted.replace(true) {
+            return;
+        }
+
+        // the renderer has already gone, along with the texture
+        if let Some(queue) = self.deletion_queue.as_ref().and_then(Weak::upgrade) {
+            queue.borrow_mut().push(self.id);
+        }
+    }
+}
+
+impl Drop for ImageTexture
+{
+    fn drop(&mut self)
+    {
+        self.delete();
+    }
 }
 
 /// `ImageSmoothingMode` defines how images are rendered when the pixels of the
@@ -69,6 +238,17 @@ pub enum ImageSmoothingMode
     Linear
 }
 
+impl ImageSmoothingMode
+{
+    pub(crate) fn filter_mode(self) -> miniquad::FilterMode
+    {
+        match self {
+            ImageSmoothingMode::NearestNeighbor => miniquad::FilterMode::Nearest,
+            ImageSmoothingMode::Linear => miniquad::FilterMode::Linear
+        }
+    }
+}
+
 /// Supported image formats.
 ///
 ///  The following image formats are supported:
@@ -152,3 +332,155 @@ impl RawBitmapData
         self.data
     }
 }
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+    use crate::color::Color;
+
+    #[test]
+    fn test_capture_alpha()
+    {
+        let pixels = [10, 20, 30, 0, 40, 50, 60, 128];
+
+        let mut opaque = pixels;
+        CaptureAlpha::Opaque.apply(&mut opaque);
+        assert_eq!(opaque, [10, 20, 30, 255, 40, 50, 60, 255]);
+
+        let mut preserved = pixels;
+        CaptureAlpha::Preserve.apply(&mut preserved);
+        assert_eq!(preserved, pixels);
+    }
+
+    #[test]
+    fn test_to_rgba8()
+    {
+        assert_eq!(
+            to_rgba8(ImageDataType::RGB, UVec2::new(2, 1), &[1, 2, 3, 4, 5, 6]).unwrap(),
+            vec![1, 2, 3, 255, 4, 5, 6, 255]
+        );
+
+        assert_eq!(
+            to_rgba8(ImageDataType::RGBA, UVec2::new(1, 1), &[1, 2, 3, 4]).unwrap(),
+            vec![1, 2, 3, 4]
+        );
+
+        assert!(to_rgba8(ImageDataType::RGBA, UVec2::new(2, 2), &[0; 12]).is_err());
+    }
+
+    #[test]
+    fn test_image_pixels()
+    {
+        let mut image = Image::gen_image_color(3, 2, Color::BLACK);
+        image.set_pixel(2, 1, Color::WHITE);
+
+        assert_eq!(image.size(), UVec2::new(3, 2));
+
+        let pixels = image.pixels().collect::<Vec<_>>();
+        assert_eq!(pixels.len(), 6);
+        assert_eq!(pixels[1], (1, 0, Color::BLACK));
+        assert_eq!(pixels[5], (2, 1, Color::WHITE));
+    }
+
+    #[test]
+    fn test_image_filters()
+    {
+        let mut image = Image::gen_image_color(3, 3, Color::from_int_rgba(0, 0, 0, 200));
+        image.set_pixel(1, 1, Color::from_int_rgba(90, 90, 90, 200));
+
+        // a blur spreads the middle pixel evenly over its neighbours, including
+        // the repeated edge pixels
+        let blurred = image.apply_kernel(&[[1.0 / 9.0; 3]; 3]);
+        assert!(blurred
+            .pixels()
+            .all(|(_, _, color)| color == Color::from_int_rgba(10, 10, 10, 200)));
+
+        image.invert();
+        assert_eq!(image.get_pixel(0, 0), Some(Color::from_int_rgba(255, 255, 255, 200)));
+        assert_eq!(image.get_pixel(1, 1), Some(Color::from_int_rgba(165, 165, 165, 200)));
+
+        image.adjust_brightness(0.5);
+        assert_eq!(image.get_pixel(0, 0), Some(Color::from_int_rgba(128, 128, 128, 200)));
+
+        let mut red = Image::gen_image_color(1, 1, Color::RED);
+        red.grayscale();
+        assert_eq!(red.get_pixel(0, 0), Some(Color::from_int_rgba(76, 76, 76, 255)));
+    }
+
+    #[test]
+    fn test_image_noise()
+    {
+        let noise = Image::gen_image_noise(16, 16, 7);
+        assert_eq!(noise.get_image_data(), Image::gen_image_noise(16, 16, 7).get_image_data());
+        assert_ne!(noise.get_image_data(), Image::gen_image_noise(16, 16, 8).get_image_data());
+        assert!(noise.pixels().all(|(_, _, color)| color.a() == 1.0));
+
+        // neighbouring pixels of value noise differ far less than white noise
+        let max_step = |image: &Image| {
+            let data = image.get_image_data();
+            data.windows(2)
+                .map(|pair| (pair[0][0] as i32 - pair[1][0] as i32).abs())
+                .max()
+                .unwrap()
+        };
+        let smooth = Image::gen_image_value_noise(16, 1, 16.0, 1, 7);
+        assert!(max_step(&smooth) < 64);
+        assert!(max_step(&noise) > max_step(&smooth));
+
+        let fbm = Image::gen_image_value_noise(16, 16, 8.0, 4, 7);
+        assert_eq!(
+            fbm.get_image_data(),
+            Image::gen_image_value_noise(16, 16, 8.0, 4, 7).get_image_data()
+        );
+    }
+
+    #[test]
+    fn test_image_pixel_out_of_bounds()
+    {
+        let mut image = Image::gen_image_color(3, 2, Color::BLACK);
+
+        // inside the image's data, but past the end of the first row
+        image.set_pixel(3, 0, Color::WHITE);
+        assert_eq!(image.get_pixel(3, 0), None);
+        assert_eq!(image.get_pixel(0, 1), Some(Color::BLACK));
+        assert_eq!(image.get_pixel(0, 2), None);
+    }
+
+    #[test]
+    fn test_image_handle_deletion()
+    {
+        let queue = DeletionQueue::default();
+        let id = miniquad::TextureId::from_raw_id(miniquad::RawId::OpenGl(7));
+        let image = ImageHandle {
+            size: UVec2::new(1, 1),
+            texture: ImageTexture::owned(id, &queue)
+        };
+
+        // dropping a clone leaves the image alive
+        drop(image.clone());
+        assert!(queue.borrow().is_empty());
+
+        // deleting one clone stops the others drawing, and deletes it once
+        let clone = image.clone();
+        image.texture.delete();
+        image.texture.delete();
+        assert_eq!(clone.texture(), None);
+        drop(image);
+        drop(clone);
+        assert_eq!(*queue.borrow(), vec![id]);
+
+        // dropping the last handle deletes the texture
+        queue.borrow_mut().clear();
+        drop(ImageHandle {
+            size: UVec2::new(1, 1),
+            texture: ImageTexture::owned(id, &queue)
+        });
+        assert_eq!(*queue.borrow(), vec![id]);
+
+        // a render target's image belongs to the target
+        queue.borrow_mut().clear();
+        ImageTexture::borrowed(id).delete();
+        assert!(queue.borrow().is_empty());
+    }
+}
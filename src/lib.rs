@@ -23,12 +23,14 @@ use {
 
 //pub mod log { pub use miniquad::{debug, error, info, trace, warn}; }
 pub use ::log as log;
+pub use miniquad::Backend;
 
 use crate::color::Color;
 use crate::dimen::{UVec2, Vec2};
 use crate::error::{BacktraceError, ErrorMessage};
-use crate::image::{ImageDataType, ImageHandle, ImageSmoothingMode, RawBitmapData};
-use crate::shape::{Polygon, Rect, Rectangle, RoundedRectangle};
+use crate::image::{CaptureAlpha, ImageDataType, ImageHandle, ImageSmoothingMode, RawBitmapData};
+use crate::image::{DeletionQueue, ImageTexture};
+use crate::shape::{LineJoin, Polygon, Rect, Rectangle, RoundedRectangle};
 use crate::window::WindowHandler;
 use crate::window::{
     UserEventSender,
@@ -40,6 +42,7 @@ use crate::window::{
 use crate::window_internal_quad::WindowQuad;
 
 pub mod math;
+mod dpi;
 pub mod text;
 mod shapes;
 mod quad_gl;
@@ -129,35 +132,137 @@ impl Display for GLRendererCreationError
 /// a window for you.
 pub struct GLRenderer
 {
-    renderer: Graphics2D
+    renderer: Graphics2D,
+    fonts: Vec<text::WeakFontCache>
 }
 
 impl GLRenderer
 {
+    /// Creates a renderer for the current miniquad context.
+    ///
+    /// Fails if the graphics driver can't compile the renderer's shaders,
+    /// which can happen with old or unusual GPUs.
     pub fn new_for_quad(
-        ) -> Self
+        ) -> Result<Self, BacktraceError<GLRendererCreationError>>
     {
         let mut ctx: Box<dyn miniquad::RenderingBackend> =
             miniquad::window::new_rendering_backend();
 
-        let gl = QuadGl::new(&mut *ctx);
+        let gl = QuadGl::new(&mut *ctx).map_err(|err| {
+            GLRendererCreationError::msg_with_cause("Failed to compile shaders", err)
+        })?;
         let texture_batcher = crate::texture::Batcher::new(&mut *ctx);
         let renderer = Graphics2D {
             renderer: ctx,
             gl:  gl,
             textures: crate::texture::TexturesContext::new(),
             texture_batcher: texture_batcher,
+            shape_segments: Graphics2D::DEFAULT_SHAPE_SEGMENTS,
+            shape_antialiasing: false,
+            clear_color: None,
+            pixel_canvas: None,
+            pending_pixel_canvas: None,
+            render_target: None,
+            capture_next_frame: None,
+            captured_frame: None,
+            last_frame_stats: FrameStats::default(),
+            deleted_textures: DeletionQueue::default(),
+            clear_texture_atlas_requested: false,
+            rebuild_texture_atlas_requested: false,
+            deleted_render_targets: Vec::new(),
         };
 
-        GLRenderer { renderer }
+        Ok(GLRenderer {
+            renderer,
+            fonts: Vec::new()
+        })
     }
 
     pub fn create_font_from_bytes(&mut self, bytes: &[u8]) -> Result<crate::text::Font, i32>
     {
-        let f = text::load_ttf_font_from_bytes(&mut *self.renderer.renderer, bytes).unwrap();
+        self.create_font_from_bytes_with_options(bytes, &text::FontLoadOptions::default())
+    }
+
+    /// Like [GLRenderer::create_font_from_bytes], but with control over which
+    /// glyphs are pre-cached when the font is loaded, and which face is
+    /// loaded from a font collection.
+    pub fn create_font_from_bytes_with_options(
+        &mut self,
+        bytes: &[u8],
+        options: &text::FontLoadOptions
+    ) -> Result<crate::text::Font, i32>
+    {
+        let f = text::load_ttf_font_from_bytes_with_options(
+            &mut *self.renderer.renderer,
+            bytes,
+            options
+        )?;
+        self.fonts.push(f.downgrade_cache());
         Ok(f)
     }
 
+    /// Reads back the contents of the window at the end of the next frame.
+    /// Retrieve it with [GLRenderer::take_captured_frame].
+    pub(crate) fn request_frame_capture(&mut self, alpha: CaptureAlpha)
+    {
+        self.renderer.capture_next_frame = Some(alpha);
+    }
+
+    /// The frame captured after [GLRenderer::request_frame_capture], once it
+    /// has been drawn.
+    pub(crate) fn take_captured_frame(&mut self) -> Option<RawBitmapData>
+    {
+        self.renderer.captured_frame.take()
+    }
+
+    /// Discards the glyph caches of all live fonts created by this renderer,
+    /// for example because the DPI scale has changed.
+    pub(crate) fn clear_font_caches(&mut self)
+    {
+        self.fonts.retain(|font| font.clear());
+    }
+
+    /// Enables or disables sRGB-correct rendering, in which colors are blended
+    /// and gradients are interpolated in linear light before being written to
+    /// the framebuffer as sRGB. This avoids translucent overlaps looking too
+    /// dark and gradients banding through muddy midtones. Colors and images
+    /// are still given in sRGB, as usual. Disabled by default.
+    ///
+    /// Only drawing to the window itself happens in linear light. Render
+    /// targets, and the canvas of [Graphics2D::set_pixel_canvas], are plain
+    /// images which hold sRGB values, so drawing into them blends in sRGB as
+    /// usual. They are then converted correctly when drawn to the window.
+    ///
+    /// This is only supported by the OpenGL backend on native platforms, and
+    /// needs the window's framebuffer to be sRGB capable, which not every
+    /// driver provides. Enabling it clears the window to check this, and
+    /// fails, leaving sRGB rendering disabled, if the framebuffer isn't.
+    pub fn set_srgb(&mut self, enabled: bool) -> Result<(), BacktraceError<ErrorMessage>>
+    {
+        if !enabled {
+            self.renderer.set_framebuffer_srgb(false);
+            self.renderer.gl.set_srgb(false);
+            return Ok(());
+        }
+
+        if cfg!(target_arch = "wasm32") || self.renderer.backend() != Backend::OpenGl {
+            return Err(ErrorMessage::msg(
+                "sRGB rendering is only supported with native OpenGL"
+            ));
+        }
+
+        self.renderer.set_framebuffer_srgb(true);
+        if !self.renderer.is_framebuffer_srgb() {
+            self.renderer.set_framebuffer_srgb(false);
+            return Err(ErrorMessage::msg(
+                "The window's framebuffer isn't sRGB capable"
+            ));
+        }
+
+        self.renderer.gl.set_srgb(true);
+        Ok(())
+    }
+
     /// Sets the renderer viewport to the specified pixel size, in response to a
     /// change in the window size.
     pub fn set_viewport_size_pixels(&mut self, viewport_size_pixels: UVec2)
@@ -237,10 +342,42 @@ impl GLRenderer
     pub fn draw_frame<F: FnOnce(&mut Graphics2D) -> R, R>(&mut self, callback: F) -> R
     {
         //self.renderer.set_clip(None);
+        let result = callback(self.begin_frame());
+        self.commit_frame();
+        result
+    }
+
+    /// Starts drawing a frame, for when the frame needs more structure than
+    /// [GLRenderer::draw_frame] allows, such as interleaving offscreen work
+    /// with drawing to the window.
+    ///
+    /// Anything drawn to the returned `Graphics2D` is queued until
+    /// [Graphics2D::flush] or [GLRenderer::commit_frame] is called. Every
+    /// call to this method must be followed by a call to
+    /// [GLRenderer::commit_frame].
+    pub fn begin_frame(&mut self) -> &mut Graphics2D
+    {
         self.renderer.begin_frame();
-        let result = callback(&mut self.renderer);
+        &mut self.renderer
+    }
+
+    /// Finishes the frame started by [GLRenderer::begin_frame], drawing
+    /// anything still queued and presenting the frame.
+    pub fn commit_frame(&mut self)
+    {
         self.renderer.end_frame();
-        result
+
+        // now that nothing queued samples them, full glyph caches can be cleared
+        self.fonts.retain(|font| font.clear_if_full());
+    }
+
+    /// Statistics for the most recent frame drawn using
+    /// [GLRenderer::draw_frame].
+    #[inline]
+    #[must_use]
+    pub fn last_frame_stats(&self) -> FrameStats
+    {
+        self.renderer.last_frame_stats
     }
 }
 
@@ -259,16 +396,120 @@ impl Drop for GLRenderer
 ///
 /// If you are managing the GL context yourself, you must invoke
 /// [GLRenderer::draw_frame] to obtain an instance.
+///
+/// # Draw order
+///
+/// Within a frame, everything is layered in the order it was drawn: later
+/// shapes, images and text always appear on top of earlier ones. Drawing
+/// operations are batched into as few draw calls as possible, but a batch
+/// only ever grows by appending to the most recent draw call, so batching
+/// never reorders anything. To draw translucent shapes correctly over
+/// opaque ones, draw the opaque ones first and then the translucent ones
+/// from back to front; no explicit barrier is needed between the two.
+///
+/// The exception is depth testing, enabled with [Graphics2D::set_depth],
+/// under which depth takes priority over draw order.
 pub struct Graphics2D
 {
     renderer: Box<dyn miniquad::RenderingBackend>,
     gl: QuadGl,
     textures: crate::texture::TexturesContext,
     texture_batcher: crate::texture::Batcher,
+    shape_segments: u8,
+    shape_antialiasing: bool,
+    clear_color: Option<Color>,
+    pixel_canvas: Option<PixelCanvas>,
+    // the canvas size to switch to at the start of the next frame, or None
+    // to go back to the window
+    pending_pixel_canvas: Option<Option<UVec2>>,
+    render_target: Option<RenderTarget>,
+    capture_next_frame: Option<CaptureAlpha>,
+    captured_frame: Option<RawBitmapData>,
+    last_frame_stats: FrameStats,
+    deleted_textures: DeletionQueue,
+    // atlas changes wait for the end of the frame, as quads already queued
+    // this frame hold coordinates into the atlas as it is
+    clear_texture_atlas_requested: bool,
+    rebuild_texture_atlas_requested: bool,
+    deleted_render_targets: Vec<miniquad::RenderPass>,
+}
+
+/// Statistics describing how a frame was submitted to the GPU, useful for
+/// seeing how well drawing operations are being batched together.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct FrameStats
+{
+    /// The number of draw calls issued.
+    pub draw_calls: usize,
+    /// The total number of vertices submitted across all draw calls.
+    pub vertices: usize,
+    /// The total number of indices submitted across all draw calls.
+    pub indices: usize
+}
+
+/// A point in a mesh drawn using [Graphics2D::draw_indexed].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Vertex
+{
+    /// The position of the vertex, in pixels.
+    pub position: Vec2,
+    /// The image coordinates at the vertex, where `(0.0, 0.0)` is the top left
+    /// of the image and `(1.0, 1.0)` the bottom right. Ignored when no image
+    /// is given.
+    pub uv: Vec2,
+    /// The color of the vertex, which is multiplied with the image.
+    pub color: Color
+}
+
+/// An offscreen image which can be drawn into instead of the window, and then
+/// drawn like any other image.
+///
+/// Created using [Graphics2D::create_render_target]. Note: this can only be
+/// used in the graphics context in which it was created.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderTarget
+{
+    size: UVec2,
+    pass: miniquad::RenderPass
+}
+
+impl RenderTarget
+{
+    /// Returns the size of the render target in pixels.
+    pub fn size(&self) -> &UVec2
+    {
+        &self.size
+    }
+}
+
+/// Fixed-resolution render target which is scaled up to the window by an
+/// integer factor at the end of each frame.
+struct PixelCanvas
+{
+    size: UVec2,
+    texture: miniquad::TextureId,
+    pass: miniquad::RenderPass,
+}
+
+// the integer scale and top left corner, in physical window pixels, at which
+// a canvas of the given size is presented in a window of the given size
+fn pixel_canvas_placement(canvas: UVec2, screen_width: f32, screen_height: f32)
+    -> (f32, glam::Vec2)
+{
+    let (canvas_width, canvas_height) = (canvas.x as f32, canvas.y as f32);
+    let scale = (screen_width / canvas_width)
+        .min(screen_height / canvas_height)
+        .floor()
+        .max(1.);
+    let x = ((screen_width - canvas_width * scale) / 2.).floor();
+    let y = ((screen_height - canvas_height * scale) / 2.).floor();
+    (scale, glam::Vec2::new(x, y))
 }
 
 impl Graphics2D
 {
+    const DEFAULT_SHAPE_SEGMENTS: u8 = 20;
+
     /// Creates a new [ImageHandle] from the specified raw pixel data.
     ///
     /// The data provided in the `data` parameter must be in the format
@@ -284,7 +525,29 @@ impl Graphics2D
         data: &[u8]
     ) -> Result<ImageHandle, BacktraceError<ErrorMessage>>
     {
-        panic!();
+        let size = size.into();
+
+        if size.x > u16::MAX as u32 || size.y > u16::MAX as u32 {
+            return Err(ErrorMessage::msg(format!(
+                "A {}x{} image is too large",
+                size.x, size.y
+            )));
+        }
+
+        let bytes = crate::image::to_rgba8(data_type, size, data)?;
+        let texture = self
+            .renderer
+            .new_texture_from_rgba8(size.x as u16, size.y as u16, &bytes);
+        self.renderer.texture_set_filter(
+            texture,
+            smoothing_mode.filter_mode(),
+            miniquad::MipmapFilterMode::None
+        );
+
+        Ok(ImageHandle {
+            size,
+            texture: ImageTexture::owned(texture, &self.deleted_textures)
+        })
     }
 
     /// Loads an image from the provided encoded image file data.
@@ -331,12 +594,538 @@ impl Graphics2D
         panic!();
     }
 
+    /// Replaces the contents of an existing image with the specified raw pixel
+    /// data, which must be the same size as the image. This is much cheaper
+    /// than creating a new image for content which changes every frame, such
+    /// as video.
+    ///
+    /// The data provided in the `data` parameter must be in the format
+    /// specified by `data_type`.
+    pub fn update_image_from_raw_pixels(
+        &mut self,
+        image: &ImageHandle,
+        data_type: ImageDataType,
+        data: &[u8]
+    ) -> Result<(), BacktraceError<ErrorMessage>>
+    {
+        let texture = image.texture().ok_or_else(|| ErrorMessage::msg("The image has been deleted"))?;
+        let bytes = crate::image::to_rgba8(data_type, image.size, data)?;
+
+        self.renderer.texture_update(texture, &bytes);
+        self.texture_batcher.update(
+            texture,
+            0,
+            0,
+            &crate::texture::Image {
+                bytes,
+                width: image.size.x as u16,
+                height: image.size.y as u16
+            }
+        );
+
+        Ok(())
+    }
+
+    /// Replaces part of an existing image with the specified raw pixel data,
+    /// leaving the rest of the image unchanged. The data covers a rectangle of
+    /// `size` pixels, with its top left corner at `position` within the image,
+    /// and must fit entirely inside the image.
+    ///
+    /// The data provided in the `data` parameter must be in the format
+    /// specified by `data_type`.
+    pub fn update_image_part_from_raw_pixels(
+        &mut self,
+        image: &ImageHandle,
+        position: impl Into<UVec2>,
+        size: impl Into<UVec2>,
+        data_type: ImageDataType,
+        data: &[u8]
+    ) -> Result<(), BacktraceError<ErrorMessage>>
+    {
+        let position = position.into();
+        let size = size.into();
+
+        let fits = |position: u32, size: u32, image_size: u32| {
+            matches!(position.checked_add(size), Some(end) if end <= image_size)
+        };
+
+        if !fits(position.x, size.x, image.size.x) || !fits(position.y, size.y, image.size.y) {
+            return Err(ErrorMessage::msg(format!(
+                "A {}x{} region at ({}, {}) doesn't fit in a {}x{} image",
+                size.x,
+                size.y,
+                position.x,
+                position.y,
+                image.size.x,
+                image.size.y
+            )));
+        }
+
+        let texture = image.texture().ok_or_else(|| ErrorMessage::msg("The image has been deleted"))?;
+        let bytes = crate::image::to_rgba8(data_type, size, data)?;
+
+        self.renderer.texture_update_part(
+            texture,
+            position.x as i32,
+            position.y as i32,
+            size.x as i32,
+            size.y as i32,
+            &bytes
+        );
+        self.texture_batcher.update(
+            texture,
+            position.x,
+            position.y,
+            &crate::texture::Image {
+                bytes,
+                width: size.x as u16,
+                height: size.y as u16
+            }
+        );
+
+        Ok(())
+    }
+
+    /// Deletes the GPU texture behind the specified image, freeing its memory.
+    ///
+    /// The texture is also deleted once every clone of its handle has been
+    /// dropped, so this is only needed to free it before then. Any remaining
+    /// clones of the handle draw nothing afterwards.
+    ///
+    /// The texture is deleted at the end of the current frame, so anything
+    /// already drawn with it this frame still appears.
+    pub fn delete_image(&mut self, image: ImageHandle)
+    {
+        image.texture.delete();
+    }
+
     /// Fills the screen with the specified color.
+    ///
+    /// The screen is only cleared implicitly if a clear color is set using
+    /// [Graphics2D::set_clear_color], so applications which redraw every
+    /// pixel themselves can skip this. Note that the window's contents
+    /// are undefined at the start of a frame unless a pixel canvas is in use
+    /// (see [Graphics2D::set_pixel_canvas]), so effects which accumulate over
+    /// several frames, such as trails, should draw into a pixel canvas.
     pub fn clear_screen(&mut self, color: Color)
     {
-        self.renderer.clear(Some((color.r(), color.g(), color.b(), color.a())), None, None);
+        self.clear_screen_ex(color, None, None);
+    }
+
+    /// Clears the screen to `color` at the start of every frame, before
+    /// anything else is drawn, so that [Graphics2D::clear_screen] doesn't
+    /// need to be called in every frame. Calling it anyway still works, and
+    /// overrides the clear color for that frame.
+    ///
+    /// Takes effect from the next frame.
+    pub fn set_clear_color(&mut self, color: Color)
+    {
+        self.clear_color = Some(color);
+    }
+
+    /// Stops clearing the screen at the start of every frame, after
+    /// [Graphics2D::set_clear_color].
+    pub fn disable_clear_color(&mut self)
+    {
+        self.clear_color = None;
+    }
+
+    /// Returns the color the screen is cleared to at the start of every
+    /// frame, if any. See [Graphics2D::set_clear_color].
+    #[inline]
+    #[must_use]
+    pub fn clear_color(&self) -> Option<Color>
+    {
+        self.clear_color
+    }
+
+    /// Fills the specified rectangle with the specified color, replacing
+    /// whatever was there, including its alpha. Unlike
+    /// [Graphics2D::clear_screen], this is ordered along with other drawing
+    /// operations, so it can clear an area which has already been drawn to in
+    /// the current frame.
+    pub fn clear_rectangle(&mut self, rect: impl AsRef<Rectangle>, color: Color)
+    {
+        let rect = rect.as_ref();
+
+        let pipeline = self.gl.get_pipeline();
+        self.gl.pipeline(Some(self.gl.replace_pipeline()));
+        shapes::draw_rectangle(
+            &mut self.gl,
+            rect.left(),
+            rect.top(),
+            rect.width(),
+            rect.height(),
+            color
+        );
+        self.gl.pipeline(pipeline);
+    }
+
+    /// Sets the depth of everything drawn afterwards, so that overlapping
+    /// shapes, images and text are ordered by depth rather than by the order
+    /// they were drawn in.
+    ///
+    /// Depth ranges from `-1.0` to `1.0`, and higher values are drawn in
+    /// front. Passing `None` turns depth testing off again, which is also the
+    /// state at the start of each frame.
+    ///
+    /// The depth buffer must be cleared at the start of the frame, using
+    /// `clear_screen_ex(color, Some(1.0), None)`. Note that translucent pixels
+    /// still hide anything drawn behind them later, so translucent shapes
+    /// should be drawn back to front.
+    pub fn set_depth(&mut self, z: Option<f32>)
+    {
+        self.gl.depth_test(z.is_some());
+        self.gl.depth(z);
+    }
+
+    /// When enabled, shapes, images and text are drawn as the outlines of the
+    /// triangles they're made of, rather than filled. Useful for checking how
+    /// polygons and rounded shapes are tessellated.
+    pub fn set_wireframe(&mut self, enabled: bool)
+    {
+        self.gl.wireframe(enabled);
+    }
+
+    /// Applies the given transform to everything drawn afterwards, in
+    /// addition to any transforms already pushed, until the matching call to
+    /// [Graphics2D::pop_transform].
+    ///
+    /// The transform stack is emptied at the start of each frame.
+    pub fn push_transform(&mut self, transform: math::Mat4)
+    {
+        self.gl.push_model_matrix(transform);
+    }
+
+    /// Removes the transform most recently added by
+    /// [Graphics2D::push_transform]. Has no effect if none remain.
+    pub fn pop_transform(&mut self)
+    {
+        self.gl.pop_model_matrix();
+    }
+
+    /// Rotates everything drawn by `callback` by `radians` around `center`,
+    /// so that a group of shapes, images and text turns as one. Positive
+    /// angles rotate clockwise on screen.
+    ///
+    /// The rotation is pushed with [Graphics2D::push_transform] and popped
+    /// again when `callback` returns, so it combines with any transforms
+    /// already pushed.
+    pub fn with_rotation<V: Into<Vec2>, F: FnOnce(&mut Graphics2D) -> R, R>(
+        &mut self,
+        center: V,
+        radians: f32,
+        callback: F
+    ) -> R
+    {
+        let center = center.into();
+        let center = math::vec3(center.x, center.y, 0.0);
+
+        self.push_transform(
+            math::Mat4::from_translation(center)
+                * math::Mat4::from_rotation_z(radians)
+                * math::Mat4::from_translation(-center)
+        );
+        let result = callback(self);
+        self.pop_transform();
+        result
+    }
+
+    /// Multiplies the color of everything drawn afterwards by `tint`, in
+    /// addition to any tints already pushed, until the matching call to
+    /// [Graphics2D::pop_tint]. Images and text are tinted too.
+    ///
+    /// For example, a tint of `Color::from_rgba(1.0, 1.0, 1.0, 0.5)` fades
+    /// everything to half opacity, and `Color::RED` keeps only the red
+    /// component of each color.
+    ///
+    /// The tint stack is emptied at the start of each frame.
+    pub fn push_tint(&mut self, tint: Color)
+    {
+        self.gl.push_tint(tint);
+    }
+
+    /// Removes the tint most recently added by [Graphics2D::push_tint]. Has
+    /// no effect if none remain.
+    pub fn pop_tint(&mut self)
+    {
+        self.gl.pop_tint();
+    }
+
+    /// Maps a point from the current local coordinate space, as set up by
+    /// [Graphics2D::push_transform], to the screen.
+    #[must_use]
+    pub fn transform_point<V: Into<Vec2>>(&self, point: V) -> Vec2
+    {
+        let point = point.into();
+        let transformed = self
+            .gl
+            .get_active_model_matrix()
+            .transform_point3(math::vec3(point.x, point.y, 0.0));
+        Vec2::new(transformed.x, transformed.y)
+    }
+
+    /// Maps a point on the screen, such as the mouse position, into the
+    /// current local coordinate space. This is the inverse of
+    /// [Graphics2D::transform_point], and is useful for hit-testing shapes
+    /// drawn while a transform is active.
+    #[must_use]
+    pub fn inverse_transform_point<V: Into<Vec2>>(&self, point: V) -> Vec2
+    {
+        let point = point.into();
+        let transformed = self
+            .gl
+            .get_active_model_matrix()
+            .inverse()
+            .transform_point3(math::vec3(point.x, point.y, 0.0));
+        Vec2::new(transformed.x, transformed.y)
+    }
+
+    /// Ends the current batch, so that anything drawn afterwards goes into a
+    /// new draw call and is layered on top of everything drawn so far.
+    ///
+    /// Draw order is already preserved without this (see
+    /// [Graphics2D#draw-order]), so it only changes how drawing is split into
+    /// draw calls, as reported by [Graphics2D::last_frame_stats].
+    pub fn flush_batch(&mut self)
+    {
+        self.gl.break_batching();
+    }
+
+    /// Returns the graphics API being used to render, which determines the
+    /// shading language custom shaders must be written in: GLSL for
+    /// [Backend::OpenGl], or Metal Shading Language for [Backend::Metal].
+    #[must_use]
+    pub fn backend(&self) -> Backend
+    {
+        self.renderer.info().backend
+    }
+
+    /// Statistics for the previous frame. The current frame's draw calls are
+    /// only submitted once it has finished.
+    #[inline]
+    #[must_use]
+    pub fn last_frame_stats(&self) -> FrameStats
+    {
+        self.last_frame_stats
+    }
+
+    /// The number of draw calls issued by the previous frame. Shorthand for
+    /// `last_frame_stats().draw_calls`.
+    #[inline]
+    #[must_use]
+    pub fn draw_call_count(&self) -> usize
+    {
+        self.last_frame_stats.draw_calls
+    }
+
+    /// Fills the screen with the specified color, and optionally resets the
+    /// depth and stencil buffers to the given values.
+    ///
+    /// Passing `None` leaves the corresponding buffer untouched.
+    pub fn clear_screen_ex(&mut self, color: Color, depth: Option<f32>, stencil: Option<i32>)
+    {
+        // the clear color is encoded to sRGB by the window's framebuffer, like
+        // drawing, but render targets hold sRGB values as they are
+        let linear = self.gl.is_srgb() && self.current_render_pass().is_none();
+        let color = if linear { color.to_linear() } else { color };
+        let color = Some((color.r(), color.g(), color.b(), color.a()));
+
+        if let Some(pass) = self.current_render_pass() {
+            self.renderer.begin_pass(
+                Some(pass),
+                miniquad::PassAction::Clear { color, depth, stencil }
+            );
+            self.renderer.end_render_pass();
+        } else {
+            self.renderer.clear(color, depth, stencil);
+        }
+    }
+
+    /// Draws everything into a canvas of the specified size in pixels, which
+    /// is then scaled up to fill as much of the window as possible by a whole
+    /// number, and centered with black bars around it. This keeps every
+    /// canvas pixel the same size on screen, as pixel art needs.
+    ///
+    /// Takes effect from the next frame. Coordinates passed to drawing
+    /// functions are in canvas pixels rather than window pixels. Positions
+    /// reported by the window, such as the mouse position, are still in
+    /// window pixels, and can be converted with
+    /// [Graphics2D::window_to_canvas].
+    pub fn set_pixel_canvas(&mut self, width: u32, height: u32)
+    {
+        self.pending_pixel_canvas = Some(Some(UVec2::new(width, height)));
+    }
+
+    /// Goes back to drawing directly to the window, after
+    /// [Graphics2D::set_pixel_canvas].
+    ///
+    /// Takes effect from the next frame.
+    pub fn disable_pixel_canvas(&mut self)
+    {
+        self.pending_pixel_canvas = Some(None);
+    }
+
+    /// Converts a position in the window, such as the mouse position, to a
+    /// position on the canvas set by [Graphics2D::set_pixel_canvas].
+    ///
+    /// Returns `None` if the position is in the black bars around the
+    /// canvas, or if no canvas is in use.
+    #[must_use]
+    pub fn window_to_canvas<V: Into<Vec2>>(&self, point: V) -> Option<Vec2>
+    {
+        let canvas = self.pixel_canvas.as_ref()?;
+        let point = point.into();
+        let dpi = dpi::DpiScaler::current();
+        let (screen_width, screen_height) = miniquad::window::screen_size();
+        let (scale, offset) = pixel_canvas_placement(canvas.size, screen_width, screen_height);
+
+        let x = (dpi.to_physical(point.x) - offset.x) / scale;
+        let y = (dpi.to_physical(point.y) - offset.y) / scale;
+        if x < 0. || y < 0. || x >= canvas.size.x as f32 || y >= canvas.size.y as f32 {
+            return None;
+        }
+
+        Some(Vec2::new(x, y))
+    }
+
+    // switching mid-frame would split the frame between the window and the
+    // canvas, so changes are only applied here
+    fn apply_pending_pixel_canvas(&mut self)
+    {
+        let size = match self.pending_pixel_canvas.take() {
+            Some(size) => size,
+            None => return
+        };
+
+        if let Some(canvas) = self.pixel_canvas.take() {
+            self.renderer.delete_render_pass(canvas.pass);
+        }
+
+        let (width, height) = match size {
+            Some(size) => (size.x, size.y),
+            None => return
+        };
+
+        let params = miniquad::TextureParams {
+            width,
+            height,
+            min_filter: miniquad::FilterMode::Nearest,
+            mag_filter: miniquad::FilterMode::Nearest,
+            ..Default::default()
+        };
+        let texture = self.renderer.new_render_texture(params);
+        let depth = self.renderer.new_render_texture(miniquad::TextureParams {
+            format: miniquad::TextureFormat::Depth,
+            ..params
+        });
+        let pass = self.renderer.new_render_pass(texture, Some(depth));
+
+        self.pixel_canvas = Some(PixelCanvas {
+            size: UVec2::new(width, height),
+            texture,
+            pass
+        });
+    }
+
+    /// Creates an offscreen image of the specified size in pixels, which can
+    /// be drawn into using [Graphics2D::set_render_target].
+    ///
+    /// Its contents are undefined until it is first cleared or drawn into.
+    pub fn create_render_target<S: Into<UVec2>>(&mut self, size: S) -> RenderTarget
+    {
+        let size = size.into();
+        let texture = self.renderer.new_render_texture(miniquad::TextureParams {
+            width: size.x,
+            height: size.y,
+            ..Default::default()
+        });
+        let pass = self.renderer.new_render_pass(texture, None);
+
+        RenderTarget { size, pass }
+    }
+
+    /// Sends subsequent drawing to `target`, or back to the window (or pixel
+    /// canvas) if `None`. Coordinates are in pixels of the target, with
+    /// `(0, 0)` at its top left.
+    ///
+    /// Drawing returns to the window at the start of every frame.
+    pub fn set_render_target(&mut self, target: Option<&RenderTarget>)
+    {
+        // everything queued so far uses the projection of the old target
+        self.flush();
+
+        self.render_target = target.cloned();
+        self.gl.render_pass(self.current_render_pass());
+    }
+
+    /// Returns an [ImageHandle] for the contents of `target`, so that it can
+    /// be drawn in a later pass, for example full-screen with a custom
+    /// shader.
+    ///
+    /// The handle stays valid, and reflects whatever is later drawn into the
+    /// target, until the target is deleted using
+    /// [Graphics2D::delete_render_target]. The image belongs to the target,
+    /// so dropping the handle, or passing it to [Graphics2D::delete_image],
+    /// doesn't delete it.
+    pub fn render_target_image(&self, target: &RenderTarget) -> ImageHandle
+    {
+        ImageHandle {
+            size: target.size,
+            texture: ImageTexture::borrowed(self.renderer.render_pass_texture(target.pass))
+        }
+    }
+
+    /// Reads back the contents of `target` as RGBA, with rows from top to
+    /// bottom, including anything drawn into it earlier in this frame.
+    ///
+    /// Unlike the window, a render target keeps the alpha it was drawn with,
+    /// so [CaptureAlpha::Preserve] can be used to export sprites with a
+    /// transparent background. This stalls until the GPU has finished
+    /// drawing, so is best avoided every frame.
+    pub fn capture_render_target(
+        &mut self,
+        target: &RenderTarget,
+        alpha: CaptureAlpha
+    ) -> RawBitmapData
+    {
+        self.flush();
+
+        let texture = self.renderer.render_pass_texture(target.pass);
+        let mut pixels = vec![0u8; target.size.x as usize * target.size.y as usize * 4];
+        self.renderer.texture_read_pixels(texture, &mut pixels);
+        alpha.apply(&mut pixels);
+
+        RawBitmapData::new(pixels, target.size, ImageDataType::RGBA)
+    }
+
+    /// Deletes a render target, along with its image. Neither may be used
+    /// afterwards.
+    ///
+    /// As with [Graphics2D::delete_image], this happens at the end of the
+    /// current frame.
+    pub fn delete_render_target(&mut self, target: RenderTarget)
+    {
+        if self.render_target.as_ref() == Some(&target) {
+            self.set_render_target(None);
+        }
+
+        self.deleted_render_targets.push(target.pass);
     }
 
+    fn current_render_pass(&self) -> Option<miniquad::RenderPass>
+    {
+        match (&self.render_target, &self.pixel_canvas) {
+            (Some(target), _) => Some(target.pass),
+            (None, Some(canvas)) => Some(canvas.pass),
+            (None, None) => None
+        }
+    }
+
+    /// Draws text with its baseline starting at `(x, y)`, so text of
+    /// different sizes drawn at the same `y` lines up. Use
+    /// [text::TextParams::baseline] to position it by its top, middle, or
+    /// bottom instead.
     pub fn draw_text(
         &mut self,
         text: &str,
@@ -351,7 +1140,6 @@ impl Graphics2D
             &mut self.gl,
             &mut *self.renderer,
             &self.textures,
-            &mut self.texture_batcher,
             text,
             x,
             y,
@@ -361,8 +1149,98 @@ impl Graphics2D
             );
     }
 
+    /// Draws text on a single line within the specified rectangle, replacing
+    /// the end of the text with an ellipsis if it is too wide to fit. The text
+    /// is aligned to the left of the rectangle, and centered vertically.
+    ///
+    /// The text is expected to be unrotated.
+    pub fn draw_text_ellipsized(
+        &mut self,
+        text: &str,
+        rect: impl AsRef<Rectangle>,
+        font: &crate::text::Font,
+        font_size: u16,
+        params: crate::text::TextParams
+    )
+    {
+        let rect = rect.as_ref();
+        let text = font.ellipsize(text, rect.width(), font_size, &params);
+
+        self.draw_text(
+            &text,
+            rect.left(),
+            rect.top() + rect.height() / 2.0,
+            font,
+            font_size,
+            crate::text::TextParams {
+                baseline: crate::text::TextBaseline::Middle,
+                ..params
+            }
+        );
+    }
+
+    /// Draws several strings which share the same font, size, and parameters.
+    /// Each item is the text to draw and the position to draw it at, as
+    /// passed to [Graphics2D::draw_text].
+    ///
+    /// This is faster than calling [Graphics2D::draw_text] for each string, as
+    /// the glyphs of all the strings are looked up and drawn together.
+    pub fn draw_text_batch(
+        &mut self,
+        items: &[(&str, f32, f32)],
+        font: &crate::text::Font,
+        font_size: u16,
+        params: crate::text::TextParams
+    )
+    {
+        crate::text::draw_text_batch(
+            &mut self.gl,
+            &mut *self.renderer,
+            &self.textures,
+            items,
+            font,
+            font_size,
+            params
+        );
+    }
+
+    /// Lays out text once, so it can be drawn every frame using
+    /// [Graphics2D::draw_layout] without repeating the work of looking up,
+    /// measuring, and positioning each glyph. This suits text which rarely
+    /// changes, such as menu items and titles.
+    ///
+    /// The text is laid out as if drawn at `(0, 0)`, so any
+    /// [text::TextParams::rotation_pivot] is relative to the position it is
+    /// later drawn at.
+    #[must_use]
+    pub fn layout_text(
+        &self,
+        text: &str,
+        font: &crate::text::Font,
+        font_size: u16,
+        params: crate::text::TextParams
+    ) -> crate::text::TextLayout
+    {
+        crate::text::layout_text(text, font, font_size, params)
+    }
+
+    /// Draws text laid out by [Graphics2D::layout_text], as
+    /// [Graphics2D::draw_text] would draw it at `position`.
+    pub fn draw_layout<V: Into<Vec2>>(&mut self, layout: &crate::text::TextLayout, position: V)
+    {
+        let position = position.into();
+        crate::text::draw_text_layout(
+            &mut self.gl,
+            &mut *self.renderer,
+            &self.textures,
+            layout,
+            math::vec2(position.x, position.y)
+        );
+    }
+
     /// Draws a polygon with a single color, with the specified offset in
-    /// pixels.
+    /// pixels. The edges are antialiased if enabled using
+    /// [Graphics2D::set_shape_antialiasing].
     pub fn draw_polygon<V: Into<Vec2>>(
         &mut self,
         polygon: &Polygon,
@@ -370,25 +1248,103 @@ impl Graphics2D
         color: Color
     )
     {
-        panic!();
+        let offset = offset.into();
+
+        for triangle in &polygon.triangles {
+            let [v1, v2, v3] = triangle.map(|vertex| {
+                glam::Vec2::new(vertex.x + offset.x, vertex.y + offset.y)
+            });
+            shapes::draw_triangle(&mut self.gl, v1, v2, v3, color);
+        }
+
+        if self.shape_antialiasing {
+            let outline = polygon
+                .outline
+                .iter()
+                .map(|vertex| glam::Vec2::new(vertex.x + offset.x, vertex.y + offset.y))
+                .collect::<Vec<_>>();
+            let feather = self.fill_antialiasing().feather;
+            shapes::draw_fill_feather(&mut self.gl, &outline, feather, color);
+        }
+    }
+
+    /// Draws the outline of a polygon with a single color, with the specified
+    /// offset in pixels. The line is centered on the polygon's edges.
+    pub fn draw_polygon_outline<V: Into<Vec2>>(
+        &mut self,
+        polygon: &Polygon,
+        offset: V,
+        thickness: f32,
+        color: Color
+    )
+    {
+        let offset = offset.into();
+
+        let points: Vec<glam::Vec2> = polygon
+            .outline
+            .iter()
+            .map(|vertex| glam::Vec2::new(vertex.x + offset.x, vertex.y + offset.y))
+            .collect();
+
+        shapes::draw_polyline(&mut self.gl, &points, true, thickness, color, LineJoin::Miter);
+    }
+
+    /// Draws a line with a single color through each of the specified points
+    /// in turn, with its corners drawn as described by `join`. If `closed` is
+    /// true, the last point is also joined back to the first.
+    pub fn draw_polyline(
+        &mut self,
+        points: &[Vec2],
+        closed: bool,
+        thickness: f32,
+        color: Color,
+        join: LineJoin
+    )
+    {
+        let points: Vec<glam::Vec2> = points
+            .iter()
+            .map(|point| glam::Vec2::new(point.x, point.y))
+            .collect();
+
+        shapes::draw_polyline(&mut self.gl, &points, closed, thickness, color, join);
+    }
+
+    /// Draws a smooth curve which passes through each of the specified points
+    /// in turn. Between each pair of neighbouring points, the curve is drawn
+    /// as `segments_per_span` straight lines.
+    pub fn draw_spline(
+        &mut self,
+        points: &[Vec2],
+        thickness: f32,
+        color: Color,
+        segments_per_span: u8
+    )
+    {
+        let points: Vec<glam::Vec2> = points
+            .iter()
+            .map(|point| glam::Vec2::new(point.x, point.y))
+            .collect();
+
+        shapes::draw_spline(&mut self.gl, &points, thickness, color, segments_per_span);
     }
 
     /// Draws a triangle with the specified colors (one color for each corner).
     ///
-    /// The vertex positions (and associated colors) must be provided in
-    /// clockwise order.
+    /// The vertex positions may be provided in either clockwise or
+    /// anti-clockwise order, as triangles are drawn whichever way they face.
     pub fn draw_triangle_three_color(
         &mut self,
-        vertex_positions_clockwise: [Vec2; 3],
-        vertex_colors_clockwise: [Color; 3]
+        vertex_positions: [Vec2; 3],
+        vertex_colors: [Color; 3]
     )
     {
-        //panic!();
+        let vp = vertex_positions.map(|position| glam::Vec2::new(position.x, position.y));
+        shapes::draw_triangle_three_color(&mut self.gl, vp[0], vp[1], vp[2], vertex_colors);
     }
 
     /// Draws part of an image, tinted with the provided colors, at the
     /// specified location. The sub-image will be scaled to fill the
-    /// triangle described by the vertices in `vertex_positions_clockwise`.
+    /// triangle described by the vertices in `vertex_positions`.
     ///
     /// The coordinates in `image_coords_normalized` should be in the range
     /// `0.0` to `1.0`, and define the portion of the source image which
@@ -398,47 +1354,55 @@ impl Graphics2D
     /// component in the image pixel by the corresponding color component in
     /// the `color` parameter.
     ///
-    /// The vertex positions (and associated colors and image coordinates) must
-    /// be provided in clockwise order.
+    /// The vertex positions (and associated colors and image coordinates) may
+    /// be provided in either clockwise or anti-clockwise order.
     pub fn draw_triangle_image_tinted_three_color(
         &mut self,
-        vertex_positions_clockwise: [Vec2; 3],
+        vertex_positions: [Vec2; 3],
         vertex_colors: [Color; 3],
         image_coords_normalized: [Vec2; 3],
         image: &ImageHandle
     )
     {
-        panic!();
+        let vertices = [0, 1, 2].map(|i| Vertex {
+            position: vertex_positions[i],
+            uv: image_coords_normalized[i],
+            color: vertex_colors[i]
+        });
+
+        self.draw_indexed(&vertices, &[0, 1, 2], Some(image), Color::WHITE);
     }
 
     /// Draws a triangle with the specified color.
     ///
-    /// The vertex positions must be provided in clockwise order.
+    /// The vertex positions may be provided in either clockwise or
+    /// anti-clockwise order, as triangles are drawn whichever way they face.
     #[inline]
-    pub fn draw_triangle(&mut self, vertex_positions_clockwise: [Vec2; 3], color: Color)
+    pub fn draw_triangle(&mut self, vertex_positions: [Vec2; 3], color: Color)
     {
-        //self.draw_triangle_three_color(vertex_positions_clockwise, [color, color, color]);
+        //self.draw_triangle_three_color(vertex_positions, [color, color, color]);
         shapes::draw_triangle(
             &mut self.gl, 
-            glam::Vec2::new(vertex_positions_clockwise[0].x, vertex_positions_clockwise[0].y),
-            glam::Vec2::new(vertex_positions_clockwise[1].x, vertex_positions_clockwise[1].y),
-            glam::Vec2::new(vertex_positions_clockwise[2].x, vertex_positions_clockwise[2].y),
+            glam::Vec2::new(vertex_positions[0].x, vertex_positions[0].y),
+            glam::Vec2::new(vertex_positions[1].x, vertex_positions[1].y),
+            glam::Vec2::new(vertex_positions[2].x, vertex_positions[2].y),
             color);
     }
 
     /// Draws a quadrilateral with the specified colors (one color for each
     /// corner).
     ///
-    /// The vertex positions (and associated colors) must be provided in
-    /// clockwise order.
+    /// The vertex positions (and associated colors) must be provided in order
+    /// around the edge of the quadrilateral, either clockwise or
+    /// anti-clockwise.
     #[inline]
     pub fn draw_quad_four_color(
         &mut self,
-        vertex_positions_clockwise: [Vec2; 4],
+        vertex_positions: [Vec2; 4],
         vertex_colors: [Color; 4]
     )
     {
-        let vp = vertex_positions_clockwise;
+        let vp = vertex_positions;
         let vc = vertex_colors;
 
         self.draw_triangle_three_color([vp[0], vp[1], vp[2]], [vc[0], vc[1], vc[2]]);
@@ -448,20 +1412,20 @@ impl Graphics2D
 
     /// Draws a quadrilateral with the specified color.
     ///
-    /// The vertex positions must be provided in clockwise order.
+    /// The vertex positions must be provided in order around the edge of the
+    /// quadrilateral, either clockwise or anti-clockwise.
     #[inline]
-    pub fn draw_quad(&mut self, vertex_positions_clockwise: [Vec2; 4], color: Color)
+    pub fn draw_quad(&mut self, vertex_positions: [Vec2; 4], color: Color)
     {
         self.draw_quad_four_color(
-            vertex_positions_clockwise,
+            vertex_positions,
             [color, color, color, color]
         );
     }
 
     /// Draws part of an image, tinted with the provided colors, at the
     /// specified location. The sub-image will be scaled to fill the
-    /// quadrilateral described by the vertices in
-    /// `vertex_positions_clockwise`.
+    /// quadrilateral described by the vertices in `vertex_positions`.
     ///
     /// The coordinates in `image_coords_normalized` should be in the range
     /// `0.0` to `1.0`, and define the portion of the source image which
@@ -472,17 +1436,18 @@ impl Graphics2D
     /// the `color` parameter.
     ///
     /// The vertex positions (and associated colors and image coordinates) must
-    /// be provided in clockwise order.
+    /// be provided in order around the edge of the quadrilateral, either
+    /// clockwise or anti-clockwise.
     #[inline]
     pub fn draw_quad_image_tinted_four_color(
         &mut self,
-        vertex_positions_clockwise: [Vec2; 4],
+        vertex_positions: [Vec2; 4],
         vertex_colors: [Color; 4],
         image_coords_normalized: [Vec2; 4],
         image: &ImageHandle
     )
     {
-        let vp = vertex_positions_clockwise;
+        let vp = vertex_positions;
         let vc = vertex_colors;
         let ic = image_coords_normalized;
 
@@ -531,89 +1496,424 @@ impl Graphics2D
                 *rect.bottom_right(),
                 rect.bottom_left()
             ],
-            [color, color, color, color],
-            [
-                *image_coords_normalized.top_left(),
-                image_coords_normalized.top_right(),
-                *image_coords_normalized.bottom_right(),
-                image_coords_normalized.bottom_left()
-            ],
-            image
+            [color, color, color, color],
+            [
+                *image_coords_normalized.top_left(),
+                image_coords_normalized.top_right(),
+                *image_coords_normalized.bottom_right(),
+                image_coords_normalized.bottom_left()
+            ],
+            image
+        );
+    }
+
+    /// Draws an image, tinted with the provided color, at the specified
+    /// location. The image will be scaled to fill the pixel coordinates in
+    /// the provided rectangle.
+    ///
+    /// The tinting is performed by for each pixel by multiplying each color
+    /// component in the image pixel by the corresponding color component in
+    /// the `color` parameter.
+    #[inline]
+    pub fn draw_rectangle_image_tinted(
+        &mut self,
+        rect: impl AsRef<Rectangle>,
+        color: Color,
+        image: &ImageHandle
+    )
+    {
+        self.draw_rectangle_image_subset_tinted(
+            rect,
+            color,
+            Rectangle::new(Vec2::ZERO, Vec2::new(1.0, 1.0)),
+            image
+        );
+    }
+
+    /// Draws an image at the specified location. The image will be
+    /// scaled to fill the pixel coordinates in the provided rectangle.
+    #[inline]
+    pub fn draw_rectangle_image(
+        &mut self,
+        rect: impl AsRef<Rectangle>,
+        image: &ImageHandle
+    )
+    {
+        self.draw_rectangle_image_tinted(rect, Color::WHITE, image);
+    }
+
+    /// Draws an image at the specified pixel location. The image will be
+    /// drawn at its original size with no scaling.
+    #[inline]
+    pub fn draw_image<P: Into<Vec2>>(&mut self, position: P, image: &ImageHandle)
+    {
+        self.draw_image_tinted(position, Color::WHITE, image);
+    }
+
+    /// Draws an image, tinted with the provided color, at the specified pixel
+    /// location. The image will be drawn at its original size with no
+    /// scaling.
+    ///
+    /// The tinting is performed by for each pixel by multiplying each color
+    /// component in the image pixel by the corresponding color component in
+    /// the `color` parameter.
+    pub fn draw_image_tinted<P: Into<Vec2>>(
+        &mut self,
+        position: P,
+        color: Color,
+        image: &ImageHandle
+    )
+    {
+        let position = position.into();
+
+        let texture = match image.texture() {
+            Some(texture) => texture,
+            None => return
+        };
+
+        crate::texture::draw_texture_ex(
+            &mut self.gl,
+            &mut *self.renderer,
+            &self.textures,
+            &mut self.texture_batcher,
+            &crate::texture::Texture2D::from_miniquad_texture(texture),
+            position.x,
+            position.y,
+            color,
+            Default::default()
+        );
+    }
+
+    /// Draws an image onto an arbitrary quad, which needn't be rectangular or
+    /// axis-aligned. Each of `corners` is mapped to the image coordinate at
+    /// the same index in `uvs`, where `(0, 0)` is the top left of the image
+    /// and `(1, 1)` the bottom right. The corners should be given in order
+    /// around the quad.
+    ///
+    /// UVs outside the `0.0` to `1.0` range aren't supported.
+    pub fn draw_texture_quad(
+        &mut self,
+        image: &ImageHandle,
+        corners: [Vec2; 4],
+        uvs: [Vec2; 4],
+        color: Color
+    )
+    {
+        let texture = match image.texture() {
+            Some(texture) => texture,
+            None => return
+        };
+
+        crate::texture::draw_texture_quad(
+            &mut self.gl,
+            &mut *self.renderer,
+            &self.textures,
+            &mut self.texture_batcher,
+            &crate::texture::Texture2D::from_miniquad_texture(texture),
+            [0, 1, 2, 3].map(|i| {
+                (
+                    glam::vec2(corners[i].x, corners[i].y),
+                    glam::vec2(uvs[i].x, uvs[i].y)
+                )
+            }),
+            color
+        );
+    }
+
+    /// Fills `dest` by repeating the image, with each copy drawn at
+    /// `tile_size` pixels and the first placed at the top left of `dest`.
+    ///
+    /// Nothing is drawn unless both sides of `tile_size` are positive.
+    ///
+    /// This is drawn as a single quad, and sets the image's wrap mode to
+    /// repeat. The wrap mode isn't restored afterwards, so any later drawing
+    /// of the image which samples outside it, such as
+    /// [Graphics2D::draw_indexed], repeats it too. On WebGL 1, only
+    /// images with power of two dimensions can be tiled.
+    pub fn draw_texture_tiled<S: Into<Vec2>>(
+        &mut self,
+        image: &ImageHandle,
+        dest: impl AsRef<Rectangle>,
+        tile_size: S,
+        color: Color
+    )
+    {
+        let dest = dest.as_ref();
+        let tile_size = tile_size.into();
+
+        let texture = match image.texture() {
+            Some(texture) => texture,
+            None => return
+        };
+
+        crate::texture::draw_texture_tiled(
+            &mut self.gl,
+            &mut *self.renderer,
+            &self.textures,
+            &crate::texture::Texture2D::from_miniquad_texture(texture),
+            crate::math::Rect::from(dest),
+            glam::vec2(tile_size.x, tile_size.y),
+            color
+        );
+    }
+
+    /// Draws many parts of one image, such as the tiles of a tilemap. Each
+    /// entry in `quads` is `(source, dest, color)`: the `source` rectangle,
+    /// in pixels of the image, is stretched over the `dest` rectangle and
+    /// tinted by `color`.
+    ///
+    /// The image is bound once for all of the quads, which are submitted
+    /// together, so they are drawn in as few draw calls as possible no
+    /// matter how many there are.
+    pub fn draw_texture_multi(
+        &mut self,
+        image: &ImageHandle,
+        quads: &[(Rectangle, Rectangle, Color)]
+    )
+    {
+        let texture = match image.texture() {
+            Some(texture) => texture,
+            None => return
+        };
+
+        crate::texture::draw_texture_multi(
+            &mut self.gl,
+            &mut *self.renderer,
+            &self.textures,
+            &mut self.texture_batcher,
+            &crate::texture::Texture2D::from_miniquad_texture(texture),
+            quads
+                .iter()
+                .map(|(source, dest, color)| (source.into(), dest.into(), *color))
+        );
+    }
+
+    /// Draws a mesh of triangles, each made up of three consecutive entries in
+    /// `indices`, which are indices into `vertices`. The whole mesh shares one
+    /// image, or none, so it is drawn with as few draw calls as possible. This
+    /// suits tilemaps and other large batches of sprites from a single image.
+    ///
+    /// The color of each vertex is multiplied by `color`.
+    ///
+    /// The image is sampled directly rather than from the texture atlas, so
+    /// image coordinates outside `0.0` to `1.0` follow the image's wrap mode.
+    ///
+    /// Nothing is drawn, and a warning is logged, if `indices` refers past the
+    /// end of `vertices`.
+    pub fn draw_indexed(
+        &mut self,
+        vertices: &[Vertex],
+        indices: &[u16],
+        image: Option<&ImageHandle>,
+        color: Color
+    )
+    {
+        if indices.iter().any(|index| *index as usize >= vertices.len()) {
+            log::warn!("draw_indexed() given an index past the end of its vertices, skipping");
+            return;
+        }
+
+        let vertices: Vec<quad_gl::Vertex> = vertices
+            .iter()
+            .map(|vertex| {
+                quad_gl::Vertex::new(
+                    vertex.position.x,
+                    vertex.position.y,
+                    0.0,
+                    vertex.uv.x,
+                    vertex.uv.y,
+                    Color::from_rgba(
+                        vertex.color.r() * color.r(),
+                        vertex.color.g() * color.g(),
+                        vertex.color.b() * color.b(),
+                        vertex.color.a() * color.a()
+                    )
+                )
+            })
+            .collect();
+
+        match image.map(ImageHandle::texture) {
+            Some(Some(texture)) => self.gl.texture(
+                &self.textures,
+                Some(&crate::texture::Texture2D::from_miniquad_texture(texture))
+            ),
+            // a deleted image draws nothing
+            Some(None) => return,
+            None => self.gl.texture_none()
+        }
+        self.gl.draw_mode(quad_gl::DrawMode::Triangles);
+        self.gl.geometry_split(&vertices, indices);
+    }
+
+    /// Draws a single-color rectangle at the specified location. The
+    /// coordinates of the rectangle are specified in pixels.
+    #[inline]
+    pub fn draw_rectangle(&mut self, rect: impl AsRef<Rectangle>, color: Color)
+    {
+        let rect = rect.as_ref();
+
+        /*
+        self.draw_quad(
+            [
+                *rect.top_left(),
+                rect.top_right(),
+                *rect.bottom_right(),
+                rect.bottom_left()
+            ],
+            color
         );
+        */
+        //log::info!("rect: {:?} {:?}", rect, color);
+        shapes::draw_rectangle(&mut self.gl, rect.left(), rect.top(), rect.width(), rect.height(), color);
     }
 
-    /// Draws an image, tinted with the provided color, at the specified
-    /// location. The image will be scaled to fill the pixel coordinates in
-    /// the provided rectangle.
+    /// Draws a rectangle at the specified location, blending between the
+    /// given corner colors. The colors are specified in the order: top left,
+    /// top right, bottom right, bottom left.
     ///
-    /// The tinting is performed by for each pixel by multiplying each color
-    /// component in the image pixel by the corresponding color component in
-    /// the `color` parameter.
+    /// To draw a vertical gradient, give both top corners one color and both
+    /// bottom corners another.
     #[inline]
-    pub fn draw_rectangle_image_tinted(
+    pub fn draw_rectangle_vertex_colors(
         &mut self,
         rect: impl AsRef<Rectangle>,
-        color: Color,
-        image: &ImageHandle
+        colors: [Color; 4]
     )
     {
-        self.draw_rectangle_image_subset_tinted(
-            rect,
-            color,
-            Rectangle::new(Vec2::ZERO, Vec2::new(1.0, 1.0)),
-            image
+        let rect = rect.as_ref();
+        shapes::draw_rectangle_vertex_colors(
+            &mut self.gl,
+            rect.left(),
+            rect.top(),
+            rect.width(),
+            rect.height(),
+            colors
         );
     }
 
-    /// Draws an image at the specified location. The image will be
-    /// scaled to fill the pixel coordinates in the provided rectangle.
+    /// Draws the lines of a grid of `cell_size` cells over the specified
+    /// rectangle, such as behind an editor's canvas. The grid starts from the
+    /// top left corner, and has lines along every edge of the rectangle, with
+    /// the last row and column of cells cut off to fit.
+    ///
+    /// Lines are `line_thickness` pixels thick, centered on the boundaries
+    /// between cells. With `major_every` of `Some(n)`, every `n`th line,
+    /// counting from the top and left edges, is drawn twice as thick. If the
+    /// cells are smaller than a pixel, only enough lines are drawn to keep
+    /// them a pixel apart.
+    ///
+    /// The whole grid is built up as a single batch of geometry, rather than
+    /// a separate line for each row and column. Rows and columns overlap
+    /// where they cross, so a translucent `color` is blended twice there.
     #[inline]
-    pub fn draw_rectangle_image(
+    pub fn draw_grid<S: Into<Vec2>>(
         &mut self,
         rect: impl AsRef<Rectangle>,
-        image: &ImageHandle
+        cell_size: S,
+        line_thickness: f32,
+        major_every: Option<u32>,
+        color: Color
     )
     {
-        self.draw_rectangle_image_tinted(rect, Color::WHITE, image);
+        let cell_size = cell_size.into();
+        shapes::draw_grid(
+            &mut self.gl,
+            rect.as_ref().into(),
+            math::vec2(cell_size.x, cell_size.y),
+            line_thickness,
+            major_every,
+            color
+        );
     }
 
-    /// Draws an image at the specified pixel location. The image will be
-    /// drawn at its original size with no scaling.
+    /// Fills the specified rectangle with a checkerboard of squares of
+    /// `cell_size` pixels, alternating between `color_a` and `color_b`, with
+    /// `color_a` in the top left. This is commonly drawn behind images to show
+    /// which parts of them are transparent.
+    ///
+    /// The whole checkerboard is built up as a single batch of geometry,
+    /// rather than a separate rectangle per cell.
     #[inline]
-    pub fn draw_image<P: Into<Vec2>>(&mut self, position: P, image: &ImageHandle)
+    pub fn draw_checkerboard(
+        &mut self,
+        rect: impl AsRef<Rectangle>,
+        cell_size: f32,
+        color_a: Color,
+        color_b: Color
+    )
     {
-        let position = position.into();
+        let rect = rect.as_ref();
+        shapes::draw_checkerboard(
+            &mut self.gl,
+            rect.into(),
+            cell_size,
+            color_a,
+            color_b
+        );
+    }
 
-        self.draw_rectangle_image(
-            Rectangle::new(position, position + image.size().into_f32()),
-            image
+    /// Draws a rectangle filled with `color`, with an inset shadow around its
+    /// inside edges: each edge is drawn in `shadow_color`, fading to `color`
+    /// over `shadow_size` pixels towards the middle. This gives panels the
+    /// appearance of being sunken into the surface around them.
+    #[inline]
+    pub fn draw_rectangle_inner_shadow(
+        &mut self,
+        rect: impl AsRef<Rectangle>,
+        color: Color,
+        shadow_color: Color,
+        shadow_size: f32
+    )
+    {
+        let rect = rect.as_ref();
+        shapes::draw_rectangle_inner_shadow(
+            &mut self.gl,
+            rect.into(),
+            color,
+            shadow_color,
+            shadow_size
         );
     }
 
-    /// Draws a single-color rectangle at the specified location. The
-    /// coordinates of the rectangle are specified in pixels.
+    /// Draws a single-color rectangle at the specified location, skewed by the
+    /// given horizontal and vertical proportions. The coordinates of the
+    /// rectangle are specified in pixels.
+    ///
+    /// The skew is applied about the center of the rectangle, so the center
+    /// stays in place. For example, a skew of `(0.5, 0.0)` shifts the top edge
+    /// left and the bottom edge right, each by a quarter of the height.
     #[inline]
-    pub fn draw_rectangle(&mut self, rect: impl AsRef<Rectangle>, color: Color)
+    pub fn draw_rectangle_skewed<V: Into<Vec2>>(
+        &mut self,
+        rect: impl AsRef<Rectangle>,
+        skew: V,
+        color: Color
+    )
     {
         let rect = rect.as_ref();
+        let skew = skew.into();
 
-        /*
-        self.draw_quad(
-            [
-                *rect.top_left(),
-                rect.top_right(),
-                *rect.bottom_right(),
-                rect.bottom_left()
-            ],
-            color
+        shapes::draw_rectangle_ex2(
+            &mut self.gl,
+            rect.left(),
+            rect.top(),
+            rect.width(),
+            rect.height(),
+            &shapes::DrawRectangleParams2 {
+                color,
+                skew: glam::Vec2::new(skew.x, skew.y),
+                ..Default::default()
+            }
         );
-        */
-        //log::info!("rect: {:?} {:?}", rect, color);
-        shapes::draw_rectangle(&mut self.gl, rect.left(), rect.top(), rect.width(), rect.height(), color);
     }
 
     /// Draws a single-color rounded rectangle at the specified location. The
-    /// coordinates of the rounded rectangle are specified in pixels.
+    /// coordinates of the rounded rectangle are specified in pixels, and the
+    /// radius of its corners is taken from `round_rect`.
+    ///
+    /// A [shape::RoundRect] can be created from an existing rectangle using
+    /// [Rectangle::rounded]. Rounded rectangles with integer coordinates can
+    /// be drawn after converting them with [RoundedRectangle::as_f32].
     #[inline]
     pub fn draw_rounded_rectangle(
         &mut self,
@@ -638,6 +1938,23 @@ impl Graphics2D
             );
     }
 
+    /// Draws each `(start, end)` pair as a one-pixel-wide line, using the
+    /// GPU's line primitives rather than a rectangle per line. This is much
+    /// cheaper than [Graphics2D::draw_line] for large numbers of lines, such
+    /// as debug visualizations, where thickness doesn't matter.
+    ///
+    /// Lines are best positioned at the centers of pixels, as described for
+    /// [Graphics2D::draw_line].
+    pub fn draw_line_primitives(&mut self, segments: &[(Vec2, Vec2)], color: Color)
+    {
+        let segments = segments
+            .iter()
+            .map(|(start, end)| (glam::vec2(start.x, start.y), glam::vec2(end.x, end.y)))
+            .collect::<Vec<_>>();
+
+        shapes::draw_line_primitives(&mut self.gl, &segments, color);
+    }
+
     /// Draws a single-color line between the given points, specified in pixels.
     ///
     /// # Pixel alignment
@@ -698,8 +2015,112 @@ impl Graphics2D
         );
     }
 
+    /// Draws a line like [Graphics2D::draw_line], but with its long edges
+    /// faded out over `feather` pixels, which antialiases them where
+    /// multisampling isn't available (e.g. on some WebGL contexts). The fade
+    /// is centered on the line's edges, so the line keeps its apparent
+    /// thickness. A `feather` of `1.0` is usually enough.
+    pub fn draw_line_ex<VStart: Into<Vec2>, VEnd: Into<Vec2>>(
+        &mut self,
+        start_position: VStart,
+        end_position: VEnd,
+        thickness: f32,
+        color: Color,
+        feather: f32
+    )
+    {
+        let start_position = start_position.into();
+        let end_position = end_position.into();
+
+        shapes::draw_line_ex(
+            &mut self.gl,
+            math::vec2(start_position.x, start_position.y),
+            math::vec2(end_position.x, end_position.y),
+            thickness,
+            feather,
+            color
+        );
+    }
+
+    /// Draws a line from `start_position` to `end_position` with a solid
+    /// triangular arrowhead at the end, such as for the directed edges of a
+    /// graph. The arrowhead is `head_size` pixels long and wide, and shrinks
+    /// to fit arrows shorter than that. A zero length arrow draws nothing.
+    pub fn draw_arrow<VStart: Into<Vec2>, VEnd: Into<Vec2>>(
+        &mut self,
+        start_position: VStart,
+        end_position: VEnd,
+        thickness: f32,
+        head_size: f32,
+        color: Color
+    )
+    {
+        let start_position = start_position.into();
+        let end_position = end_position.into();
+
+        shapes::draw_arrow(
+            &mut self.gl,
+            math::vec2(start_position.x, start_position.y),
+            math::vec2(end_position.x, end_position.y),
+            thickness,
+            head_size,
+            color
+        );
+    }
+
+    /// Sets the number of segments used to approximate circles and ellipses.
+    /// Higher values produce smoother curves at the cost of more vertices. By
+    /// default this is set to `20`.
+    ///
+    /// The value is clamped to the range `3` to `255`.
+    pub fn set_shape_smoothness(&mut self, segments: u32)
+    {
+        self.shape_segments = segments.clamp(3, u8::MAX as u32) as u8;
+    }
+
+    /// Returns the number of segments used to approximate circles and
+    /// ellipses. See [Graphics2D::set_shape_smoothness].
+    #[inline]
+    #[must_use]
+    pub fn shape_smoothness(&self) -> u32
+    {
+        self.shape_segments as u32
+    }
+
+    /// Enables or disables antialiasing of filled circles, ellipses and
+    /// polygons. When enabled, their edges fade out over an extra pixel
+    /// around the outside of the shape, which smooths them without relying
+    /// on multisampling (which is often unavailable on the web). Disabled by
+    /// default.
+    ///
+    /// Outlines and lines aren't affected: use [Graphics2D::draw_line_ex]
+    /// for antialiased lines.
+    pub fn set_shape_antialiasing(&mut self, enabled: bool)
+    {
+        self.shape_antialiasing = enabled;
+    }
+
+    /// Returns whether filled shapes are antialiased. See
+    /// [Graphics2D::set_shape_antialiasing].
+    #[inline]
+    #[must_use]
+    pub fn shape_antialiasing(&self) -> bool
+    {
+        self.shape_antialiasing
+    }
+
+    fn fill_antialiasing(&self) -> shapes::Antialiasing
+    {
+        if self.shape_antialiasing {
+            shapes::Antialiasing { feather: 1.0 }
+        } else {
+            shapes::Antialiasing::NONE
+        }
+    }
+
     /// Draws a circle, filled with a single color, at the specified pixel
-    /// location.
+    /// location. The edge is antialiased if enabled using
+    /// [Graphics2D::set_shape_antialiasing].
     pub fn draw_circle<V: Into<Vec2>>(
         &mut self,
         center_position: V,
@@ -707,6 +2128,119 @@ impl Graphics2D
         color: Color
     )
     {
+        let center_position = center_position.into();
+        let antialiasing = self.fill_antialiasing();
+
+        shapes::draw_circle(
+            &mut self.gl,
+            math::vec2(center_position.x, center_position.y),
+            radius,
+            self.shape_segments,
+            antialiasing,
+            color
+        );
+    }
+
+    /// Draws the outline of a circle, with the specified line thickness, at the
+    /// specified pixel location.
+    pub fn draw_circle_lines<V: Into<Vec2>>(
+        &mut self,
+        center_position: V,
+        radius: f32,
+        thickness: f32,
+        color: Color
+    )
+    {
+        let center_position = center_position.into();
+
+        shapes::draw_circle_lines(
+            &mut self.gl,
+            center_position.x,
+            center_position.y,
+            radius,
+            self.shape_segments,
+            thickness,
+            color
+        );
+    }
+
+    /// Draws a ring, filled with a single color, covering the area between two
+    /// circles of radius `inner_radius` and `outer_radius` around the
+    /// specified pixel location. Unlike drawing a smaller circle over a
+    /// larger one, the hole is left transparent.
+    pub fn draw_ring<V: Into<Vec2>>(
+        &mut self,
+        center_position: V,
+        inner_radius: f32,
+        outer_radius: f32,
+        color: Color
+    )
+    {
+        let center_position = center_position.into();
+
+        shapes::draw_ring(
+            &mut self.gl,
+            math::vec2(center_position.x, center_position.y),
+            inner_radius,
+            outer_radius,
+            self.shape_segments,
+            color
+        );
+    }
+
+    /// Draws an ellipse, filled with a single color, at the specified pixel
+    /// location. The `size` parameter gives the horizontal and vertical
+    /// radii, and `rotation` is specified in radians. The edge is antialiased
+    /// if enabled using [Graphics2D::set_shape_antialiasing].
+    pub fn draw_ellipse<V: Into<Vec2>, S: Into<Vec2>>(
+        &mut self,
+        center_position: V,
+        size: S,
+        rotation: f32,
+        color: Color
+    )
+    {
+        let center_position = center_position.into();
+        let size = size.into();
+        let antialiasing = self.fill_antialiasing();
+
+        shapes::draw_ellipse(
+            &mut self.gl,
+            math::vec2(center_position.x, center_position.y),
+            math::vec2(size.x, size.y),
+            self.shape_segments,
+            rotation,
+            antialiasing,
+            color
+        );
+    }
+
+    /// Draws the outline of an ellipse, with the specified line thickness, at
+    /// the specified pixel location. The `size` parameter gives the
+    /// horizontal and vertical radii, and `rotation` is specified in radians.
+    pub fn draw_ellipse_lines<V: Into<Vec2>, S: Into<Vec2>>(
+        &mut self,
+        center_position: V,
+        size: S,
+        rotation: f32,
+        thickness: f32,
+        color: Color
+    )
+    {
+        let center_position = center_position.into();
+        let size = size.into();
+
+        shapes::draw_ellipse_lines(
+            &mut self.gl,
+            center_position.x,
+            center_position.y,
+            size.x,
+            size.y,
+            self.shape_segments,
+            rotation,
+            thickness,
+            color
+        );
     }
 
     /// Draws a triangular subset of a circle.
@@ -745,7 +2279,7 @@ impl Graphics2D
     #[inline]
     pub fn draw_circle_section_triangular_three_color(
         &mut self,
-        vertex_positions_clockwise: [Vec2; 3],
+        vertex_positions: [Vec2; 3],
         vertex_colors: [Color; 3],
         vertex_circle_coords_normalized: [Vec2; 3]
         )
@@ -753,13 +2287,13 @@ impl Graphics2D
         /*
         shapes::draw_triangle(
             &mut self.gl, 
-            glam::Vec2::new(vertex_positions_clockwise[0].x, vertex_positions_clockwise[0].y),
-            glam::Vec2::new(vertex_positions_clockwise[1].x, vertex_positions_clockwise[1].y),
-            glam::Vec2::new(vertex_positions_clockwise[2].x, vertex_positions_clockwise[2].y),
+            glam::Vec2::new(vertex_positions[0].x, vertex_positions[0].y),
+            glam::Vec2::new(vertex_positions[1].x, vertex_positions[1].y),
+            glam::Vec2::new(vertex_positions[2].x, vertex_positions[2].y),
             vertex_colors[0]);
             */
         //self.renderer.draw_circle_section(
-            //vertex_positions_clockwise,
+            //vertex_positions,
             //vertex_colors,
             //vertex_circle_coords_normalized
         //);
@@ -773,6 +2307,44 @@ impl Graphics2D
         // TODO
     }
 
+    /// Removes every image from the internal texture atlas, releasing the
+    /// space they used.
+    ///
+    /// The least recently drawn images are evicted when
+    /// [Graphics2D::rebuild_texture_atlas] grows the atlas too large, but
+    /// applications which stream many short-lived images may wish to reclaim
+    /// the space sooner.
+    ///
+    /// The atlas is cleared at the end of the current frame, so anything
+    /// already drawn from it this frame still appears.
+    pub fn clear_texture_atlas(&mut self)
+    {
+        self.clear_texture_atlas_requested = true;
+    }
+
+    /// Sets the smoothing mode used when drawing images from the internal
+    /// texture atlas. Pixel art will usually want
+    /// [ImageSmoothingMode::NearestNeighbor].
+    ///
+    /// Fonts have their own atlas, configured separately using
+    /// [text::Font::set_filter].
+    pub fn set_texture_atlas_smoothing_mode(&mut self, smoothing_mode: ImageSmoothingMode)
+    {
+        self.texture_batcher
+            .set_filter(&mut *self.renderer, smoothing_mode.filter_mode());
+    }
+
+    /// Adds any images loaded since the internal texture atlas was last built,
+    /// and repacks the atlas to reclaim space left by evicted images. If the
+    /// atlas grows too large, the least recently drawn images are evicted.
+    ///
+    /// As with [Graphics2D::clear_texture_atlas], this happens at the end of
+    /// the current frame, after any clear.
+    pub fn rebuild_texture_atlas(&mut self)
+    {
+        self.rebuild_texture_atlas_requested = true;
+    }
+
     /// Captures a screenshot of the render window. The returned data contains
     /// the color of each pixel. Pixels are represented using a `u8` for each
     /// component (red, green, blue, and alpha). Use the `format` parameter to
@@ -784,25 +2356,216 @@ impl Graphics2D
 
     fn begin_frame(&mut self) {
         self.gl.reset();
+        // only count what this frame draws
+        self.gl.take_stats();
+        self.render_target = None;
+        self.apply_pending_pixel_canvas();
+        self.gl.render_pass(self.current_render_pass());
+
+        if let Some(color) = self.clear_color {
+            self.clear_screen(color);
+        }
     }
 
     pub(crate) fn pixel_perfect_projection_matrix(&self) -> glam::Mat4 {
+        // upside down, so that the target's image is stored top row first
+        // like any other image
+        if let Some(target) = &self.render_target {
+            let (width, height) = (target.size.x as f32, target.size.y as f32);
+            return glam::Mat4::orthographic_rh_gl(0., width, 0., height, -1., 1.);
+        }
+
+        if let Some(canvas) = &self.pixel_canvas {
+            let (width, height) = (canvas.size.x as f32, canvas.size.y as f32);
+            return glam::Mat4::orthographic_rh_gl(0., width, height, 0., -1., 1.);
+        }
+
         let (width, height) = miniquad::window::screen_size();
-        let dpi = miniquad::window::dpi_scale();
+        let dpi = dpi::DpiScaler::current();
 
-        glam::Mat4::orthographic_rh_gl(0., width / dpi, height / dpi, 0., -1., 1.)
+        glam::Mat4::orthographic_rh_gl(0., dpi.to_logical(width), dpi.to_logical(height), 0., -1., 1.)
     }
 
-    fn end_frame(&mut self) {
+    /// Draws everything queued so far in this frame, without ending the
+    /// frame. Drawing can continue afterwards, and is queued again until the
+    /// next flush or the end of the frame.
+    ///
+    /// This is only needed when other rendering must happen in between, for
+    /// example when using [GLRenderer::begin_frame] to interleave offscreen
+    /// work with drawing to the window.
+    pub fn flush(&mut self) {
         let screen_mat = self.pixel_perfect_projection_matrix();
         self.gl.draw(&mut *self.renderer, screen_mat);
+    }
+
+    fn end_frame(&mut self) {
+        self.flush();
+
+        if self.pixel_canvas.is_some() {
+            self.present_pixel_canvas();
+        }
+
+        if let Some(alpha) = self.capture_next_frame.take() {
+            self.captured_frame = self.read_window_pixels(alpha);
+        }
+
+        self.last_frame_stats = self.gl.take_stats();
+
+        let deleted_textures = std::mem::take(&mut *self.deleted_textures.borrow_mut());
+        for texture in deleted_textures {
+            self.texture_batcher.remove(texture);
+            self.renderer.delete_texture(texture);
+        }
+
+        if std::mem::take(&mut self.clear_texture_atlas_requested) {
+            self.texture_batcher.clear();
+        }
+
+        if std::mem::take(&mut self.rebuild_texture_atlas_requested) {
+            self.texture_batcher
+                .rebuild(&mut *self.renderer, &self.textures, &self.gl);
+        }
+
+        for pass in self.deleted_render_targets.drain(..) {
+            self.renderer.delete_render_pass(pass);
+        }
 
         self.renderer.commit_frame();
     }
 
+    fn set_framebuffer_srgb(&mut self, enabled: bool) {
+        if self.backend() != Backend::OpenGl {
+            return;
+        }
+
+        const GL_FRAMEBUFFER_SRGB: u32 = 0x8DB9;
+        unsafe {
+            if enabled {
+                miniquad::gl::glEnable(GL_FRAMEBUFFER_SRGB);
+            } else {
+                miniquad::gl::glDisable(GL_FRAMEBUFFER_SRGB);
+            }
+        }
+    }
+
+    /// Whether the window's framebuffer encodes what is drawn to it as sRGB,
+    /// with `GL_FRAMEBUFFER_SRGB` enabled. miniquad doesn't request an sRGB
+    /// capable framebuffer, and doesn't expose the query for one, so this
+    /// clears the window to a linear mid grey and reads it back.
+    fn is_framebuffer_srgb(&mut self) -> bool {
+        let mut pixel = [0u8; 4];
+
+        self.renderer.begin_default_pass(miniquad::PassAction::clear_color(0.5, 0.5, 0.5, 1.));
+        unsafe {
+            miniquad::gl::glReadPixels(
+                0,
+                0,
+                1,
+                1,
+                miniquad::gl::GL_RGBA,
+                miniquad::gl::GL_UNSIGNED_BYTE,
+                pixel.as_mut_ptr() as *mut _,
+            );
+        }
+        self.renderer.end_render_pass();
+
+        // encoded, linear 0.5 is stored as 188, otherwise it stays at 128
+        pixel[0] > 160
+    }
+
+    /// Reads the window's framebuffer as RGBA, with rows from top to bottom.
+    /// Only supported on OpenGL.
+    fn read_window_pixels(&mut self, alpha: CaptureAlpha) -> Option<RawBitmapData> {
+        if self.backend() != Backend::OpenGl {
+            log::warn!("Frame capture is only supported with OpenGL");
+            return None;
+        }
+
+        let (width, height) = miniquad::window::screen_size();
+        let (width, height) = (width as usize, height as usize);
+        let mut pixels = vec![0u8; width * height * 4];
+
+        self.renderer.begin_default_pass(miniquad::PassAction::Nothing);
+        unsafe {
+            miniquad::gl::glReadPixels(
+                0,
+                0,
+                width as i32,
+                height as i32,
+                miniquad::gl::GL_RGBA,
+                miniquad::gl::GL_UNSIGNED_BYTE,
+                pixels.as_mut_ptr() as *mut _,
+            );
+        }
+        self.renderer.end_render_pass();
+
+        // GL rows start at the bottom
+        let mut flipped = pixels
+            .chunks_exact(width * 4)
+            .rev()
+            .flatten()
+            .copied()
+            .collect::<Vec<_>>();
+        alpha.apply(&mut flipped);
+
+        Some(RawBitmapData::new(
+            flipped,
+            UVec2::new(width as u32, height as u32),
+            ImageDataType::RGBA,
+        ))
+    }
+
+    /// Draws the pixel canvas to the window at the largest integer scale that
+    /// fits, centered.
+    fn present_pixel_canvas(&mut self) {
+        let canvas = self.pixel_canvas.as_ref().unwrap();
+        let texture = crate::texture::Texture2D {
+            texture: crate::texture::TextureHandle::Unmanaged(canvas.texture),
+        };
+
+        let (screen_width, screen_height) = miniquad::window::screen_size();
+        let (scale, offset) = pixel_canvas_placement(canvas.size, screen_width, screen_height);
+        let (width, height) = (canvas.size.x as f32 * scale, canvas.size.y as f32 * scale);
+        let (x, y) = (offset.x, offset.y);
+
+        self.renderer.begin_default_pass(miniquad::PassAction::clear_color(0., 0., 0., 1.));
+        self.renderer.end_render_pass();
+
+        let wireframe = self.gl.is_wireframe();
+        let premultiplied_alpha = self.gl.is_premultiplied_alpha();
+        let pipeline = self.gl.get_pipeline();
+        self.gl.clear_draw_calls();
+        self.gl.reset();
+        self.gl.render_pass(None);
+        self.gl.wireframe(false);
+        self.gl.premultiplied_alpha(false);
+        self.gl.pipeline(None);
+        self.gl.texture(&self.textures, Some(&texture));
+        self.gl.draw_mode(quad_gl::DrawMode::Triangles);
+
+        // render targets are stored bottom-up
+        let vertices = [
+            quad_gl::Vertex::new(x, y, 0., 0., 1., Color::WHITE),
+            quad_gl::Vertex::new(x + width, y, 0., 1., 1., Color::WHITE),
+            quad_gl::Vertex::new(x + width, y + height, 0., 1., 0., Color::WHITE),
+            quad_gl::Vertex::new(x, y + height, 0., 0., 0., Color::WHITE),
+        ];
+        self.gl.geometry(&vertices, &[0, 1, 2, 0, 2, 3]);
+
+        let window_mat =
+            glam::Mat4::orthographic_rh_gl(0., screen_width, screen_height, 0., -1., 1.);
+        self.gl.draw(&mut *self.renderer, window_mat);
+
+        self.gl.wireframe(wireframe);
+        self.gl.premultiplied_alpha(premultiplied_alpha);
+        self.gl.pipeline(pipeline);
+    }
+
 }
 
 /// Struct representing a window.
+///
+/// Only a single window is supported per process. See [Window::run_loop].
 pub struct Window<UserEventType = ()>
 where
     UserEventType: 'static
@@ -889,11 +2652,60 @@ impl<UserEventType: 'static> Window<UserEventType>
     /// Once the event loop finishes running, the entire app will terminate,
     /// even if other threads are still running. See
     /// [window::WindowHelper::terminate_loop()].
+    ///
+    /// Only one window may be run per process: the underlying windowing
+    /// backend keeps global state which can't be reset once the first event
+    /// loop has started. To show several views at once, draw them into
+    /// separate regions of a single window.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the window can't be run, in any of the cases where
+    /// [Window::try_run_loop] returns an error.
     pub fn run_loop<H>(self, handler: H)
     where
         H: WindowHandler<UserEventType> + 'static
     {
-        self.window_impl.run_loop(handler);
+        if let Err(err) = self.try_run_loop(handler) {
+            panic!("Failed to run the window: {:?}", err);
+        }
+    }
+
+    /// Like [Window::run_loop], but returns an error instead of panicking if
+    /// the window can't be run.
+    ///
+    /// Returns [WindowCreationError::RendererCreationFailed] if the renderer
+    /// can't be created, for example because the graphics driver rejects its
+    /// shaders. In that case the window closes again without the handler
+    /// being called, leaving the application free to report the error in
+    /// another way.
+    ///
+    /// Returns [WindowCreationError::WindowAlreadyRunning] if a window has
+    /// already been run in this process.
+    pub fn try_run_loop<H>(self, handler: H) -> Result<(), BacktraceError<WindowCreationError>>
+    where
+        H: WindowHandler<UserEventType> + 'static
+    {
+        self.window_impl.run_loop(handler)
     }
 }
 
+#[cfg(test)]
+mod test
+{
+    use crate::dimen::UVec2;
+    use crate::pixel_canvas_placement;
+
+    #[test]
+    fn test_pixel_canvas_placement()
+    {
+        let (scale, offset) = pixel_canvas_placement(UVec2::new(320, 180), 1366., 768.);
+        assert_eq!(scale, 4.);
+        assert_eq!(offset, glam::Vec2::new(43., 24.));
+
+        // never scaled below one, even if the window is too small
+        let (scale, offset) = pixel_canvas_placement(UVec2::new(320, 180), 200., 100.);
+        assert_eq!(scale, 1.);
+        assert_eq!(offset, glam::Vec2::new(-60., -40.));
+    }
+}
@@ -1,6 +1,9 @@
 use glam::*;
 
 /// A 2D rectangle, defined by its top-left corner, width and height.
+///
+/// Converts to and from [crate::shape::Rect], the rectangle type taken by
+/// the drawing functions of [crate::Graphics2D], using `From`/`Into`.
 #[derive(Clone, Copy, Debug, Default, PartialEq)]
 pub struct Rect {
     pub x: f32,
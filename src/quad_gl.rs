@@ -6,13 +6,18 @@ pub use miniquad::{FilterMode, ShaderError, TextureId as MiniquadTexture};
 
 use crate::{
     Color, 
+    dpi::DpiScaler,
+    FrameStats,
     //logging::warn, 
     //telemetry, 
     texture::Texture2D, 
     //Error
     };
 
-use std::{collections::BTreeMap, sync::Arc};
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+};
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum DrawMode {
@@ -41,6 +46,7 @@ struct DrawCall {
     uniforms: Option<Vec<u8>>,
     render_pass: Option<RenderPass>,
     capture: bool,
+    premultiplied_alpha: bool,
 }
 
 #[repr(C)]
@@ -125,6 +131,7 @@ impl DrawCall {
             uniforms,
             render_pass,
             capture: false,
+            premultiplied_alpha: false,
         }
     }
 
@@ -211,7 +218,7 @@ mod snapshotter_shader {
 }
 
 impl MagicSnapshotter {
-    fn new(ctx: &mut dyn RenderingBackend) -> MagicSnapshotter {
+    fn new(ctx: &mut dyn RenderingBackend) -> Result<MagicSnapshotter, ShaderError> {
         let shader = ctx
             .new_shader(
                 match ctx.info().backend {
@@ -224,8 +231,7 @@ impl MagicSnapshotter {
                     },
                 },
                 snapshotter_shader::meta(),
-            )
-            .unwrap_or_else(|e| panic!("Failed to load shader: {}", e));
+            )?;
 
         let pipeline = ctx.new_pipeline(
             &[BufferLayout::default()],
@@ -263,12 +269,12 @@ impl MagicSnapshotter {
             images: vec![ctx.new_texture_from_rgba8(1, 1, &[0, 0, 0, 0])],
         };
 
-        MagicSnapshotter {
+        Ok(MagicSnapshotter {
             pipeline,
             bindings,
             pass: None,
             screen_texture: None,
-        }
+        })
     }
 
     #[cfg(not)]
@@ -336,11 +342,16 @@ struct GlState {
     clip: Option<(i32, i32, i32, i32)>,
     viewport: Option<(i32, i32, i32, i32)>,
     model_stack: Vec<glam::Mat4>,
+    tint_stack: Vec<Color>,
     pipeline: Option<GlPipeline>,
     depth_test_enable: bool,
+    depth: Option<f32>,
+    premultiplied_alpha: bool,
+    wireframe: bool,
 
     break_batching: bool,
-    snapshotter: MagicSnapshotter,
+    /// Only `None` in a headless `QuadGl`, which never draws.
+    snapshotter: Option<MagicSnapshotter>,
 
     render_pass: Option<RenderPass>,
     capture: bool,
@@ -350,6 +361,10 @@ impl GlState {
     fn model(&self) -> glam::Mat4 {
         *self.model_stack.last().unwrap()
     }
+
+    fn tint(&self) -> Color {
+        *self.tint_stack.last().unwrap()
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -426,8 +441,14 @@ impl PipelinesStorage {
     const LINES_PIPELINE: GlPipeline = GlPipeline(1);
     const TRIANGLES_DEPTH_PIPELINE: GlPipeline = GlPipeline(2);
     const LINES_DEPTH_PIPELINE: GlPipeline = GlPipeline(3);
-
-    fn new(ctx: &mut dyn RenderingBackend) -> PipelinesStorage {
+    const TRIANGLES_PREMULTIPLIED_PIPELINE: GlPipeline = GlPipeline(4);
+    const LINES_PREMULTIPLIED_PIPELINE: GlPipeline = GlPipeline(5);
+    const TRIANGLES_DEPTH_PREMULTIPLIED_PIPELINE: GlPipeline = GlPipeline(6);
+    const LINES_DEPTH_PREMULTIPLIED_PIPELINE: GlPipeline = GlPipeline(7);
+    const SDF_TEXT_PIPELINE: GlPipeline = GlPipeline(8);
+    const TRIANGLES_REPLACE_PIPELINE: GlPipeline = GlPipeline(9);
+
+    fn new(ctx: &mut dyn RenderingBackend) -> Result<PipelinesStorage, ShaderError> {
         let shader = ctx
             .new_shader(
                 match ctx.info().backend {
@@ -440,8 +461,7 @@ impl PipelinesStorage {
                     },
                 },
                 shader::meta(),
-            )
-            .unwrap_or_else(|e| panic!("Failed to load shader: {}", e));
+            )?;
 
         let params = PipelineParams {
             color_blend: Some(BlendState::new(
@@ -452,6 +472,15 @@ impl PipelinesStorage {
             ..Default::default()
         };
 
+        let premultiplied_params = PipelineParams {
+            color_blend: Some(BlendState::new(
+                Equation::Add,
+                BlendFactor::One,
+                BlendFactor::OneMinusValue(BlendValue::SourceAlpha),
+            )),
+            ..Default::default()
+        };
+
         let mut storage = PipelinesStorage {
             pipelines: Default::default(),
             pipelines_amount: 0,
@@ -513,7 +542,112 @@ impl PipelinesStorage {
         );
         assert_eq!(lines_depth_pipeline, Self::LINES_DEPTH_PIPELINE);
 
-        storage
+        let triangles_premultiplied_pipeline = storage.make_pipeline(
+            ctx,
+            shader,
+            PipelineParams {
+                primitive_type: PrimitiveType::Triangles,
+                ..premultiplied_params
+            },
+            false,
+            vec![],
+            vec![],
+        );
+        assert_eq!(triangles_premultiplied_pipeline, Self::TRIANGLES_PREMULTIPLIED_PIPELINE);
+
+        let lines_premultiplied_pipeline = storage.make_pipeline(
+            ctx,
+            shader,
+            PipelineParams {
+                primitive_type: PrimitiveType::Lines,
+                ..premultiplied_params
+            },
+            false,
+            vec![],
+            vec![],
+        );
+        assert_eq!(lines_premultiplied_pipeline, Self::LINES_PREMULTIPLIED_PIPELINE);
+
+        let triangles_depth_premultiplied_pipeline = storage.make_pipeline(
+            ctx,
+            shader,
+            PipelineParams {
+                depth_write: true,
+                depth_test: Comparison::LessOrEqual,
+                primitive_type: PrimitiveType::Triangles,
+                ..premultiplied_params
+            },
+            false,
+            vec![],
+            vec![],
+        );
+        assert_eq!(
+            triangles_depth_premultiplied_pipeline,
+            Self::TRIANGLES_DEPTH_PREMULTIPLIED_PIPELINE
+        );
+
+        let lines_depth_premultiplied_pipeline = storage.make_pipeline(
+            ctx,
+            shader,
+            PipelineParams {
+                depth_write: true,
+                depth_test: Comparison::LessOrEqual,
+                primitive_type: PrimitiveType::Lines,
+                ..premultiplied_params
+            },
+            false,
+            vec![],
+            vec![],
+        );
+        assert_eq!(
+            lines_depth_premultiplied_pipeline,
+            Self::LINES_DEPTH_PREMULTIPLIED_PIPELINE
+        );
+
+        let sdf_shader = ctx
+            .new_shader(
+                match ctx.info().backend {
+                    Backend::OpenGl => ShaderSource::Glsl {
+                        vertex: shader::VERTEX,
+                        fragment: shader::SDF_FRAGMENT,
+                    },
+                    Backend::Metal => ShaderSource::Msl {
+                        program: shader::SDF_METAL,
+                    },
+                },
+                shader::sdf_meta(),
+            )?;
+
+        let sdf_text_pipeline = storage.make_pipeline(
+            ctx,
+            sdf_shader,
+            PipelineParams {
+                primitive_type: PrimitiveType::Triangles,
+                ..params
+            },
+            false,
+            shader::sdf_uniforms()
+                .into_iter()
+                .map(|(name, kind)| (name.to_owned(), kind))
+                .collect(),
+            vec![],
+        );
+        assert_eq!(sdf_text_pipeline, Self::SDF_TEXT_PIPELINE);
+
+        let triangles_replace_pipeline = storage.make_pipeline(
+            ctx,
+            shader,
+            PipelineParams {
+                primitive_type: PrimitiveType::Triangles,
+                ..Default::default()
+            },
+            false,
+            vec![],
+            vec![],
+        );
+        assert_eq!(triangles_replace_pipeline, Self::TRIANGLES_REPLACE_PIPELINE);
+
+        Ok(storage)
     }
 
     fn make_pipeline(
@@ -577,12 +711,16 @@ impl PipelinesStorage {
         GlPipeline(id)
     }
 
-    fn get(&self, draw_mode: DrawMode, depth_enabled: bool) -> GlPipeline {
-        match (draw_mode, depth_enabled) {
-            (DrawMode::Triangles, false) => Self::TRIANGLES_PIPELINE,
-            (DrawMode::Triangles, true) => Self::TRIANGLES_DEPTH_PIPELINE,
-            (DrawMode::Lines, false) => Self::LINES_PIPELINE,
-            (DrawMode::Lines, true) => Self::LINES_DEPTH_PIPELINE,
+    fn get(&self, draw_mode: DrawMode, depth_enabled: bool, premultiplied_alpha: bool) -> GlPipeline {
+        match (draw_mode, depth_enabled, premultiplied_alpha) {
+            (DrawMode::Triangles, false, false) => Self::TRIANGLES_PIPELINE,
+            (DrawMode::Triangles, true, false) => Self::TRIANGLES_DEPTH_PIPELINE,
+            (DrawMode::Lines, false, false) => Self::LINES_PIPELINE,
+            (DrawMode::Lines, true, false) => Self::LINES_DEPTH_PIPELINE,
+            (DrawMode::Triangles, false, true) => Self::TRIANGLES_PREMULTIPLIED_PIPELINE,
+            (DrawMode::Triangles, true, true) => Self::TRIANGLES_DEPTH_PREMULTIPLIED_PIPELINE,
+            (DrawMode::Lines, false, true) => Self::LINES_PREMULTIPLIED_PIPELINE,
+            (DrawMode::Lines, true, true) => Self::LINES_DEPTH_PREMULTIPLIED_PIPELINE,
         }
     }
 
@@ -603,6 +741,8 @@ pub struct QuadGl {
     draw_calls_count: usize,
     state: GlState,
     start_time: f64,
+    stats: FrameStats,
+    srgb: bool,
 
     pub(crate) white_texture: miniquad::TextureId,
     pub(crate) red_texture: miniquad::TextureId,
@@ -611,22 +751,69 @@ pub struct QuadGl {
 }
 
 impl QuadGl {
-    pub fn new(ctx: &mut dyn miniquad::RenderingBackend) -> QuadGl {
+    /// Fails if the graphics driver rejects any of the built in shaders.
+    pub fn new(ctx: &mut dyn miniquad::RenderingBackend) -> Result<QuadGl, ShaderError> {
         let white_texture = ctx.new_texture_from_rgba8(1, 1, &[255, 255, 255, 255]);
         let red_texture = ctx.new_texture_from_rgba8(1, 1, &[230, 50, 50, 255]);
 
+        Ok(QuadGl::from_parts(
+            PipelinesStorage::new(ctx)?,
+            Some(MagicSnapshotter::new(ctx)?),
+            white_texture,
+            red_texture,
+        ))
+    }
+
+    /// A `QuadGl` with no graphics context behind it, for testing the geometry
+    /// generated by drawing functions. It queues geometry as usual, but must
+    /// never be drawn.
+    #[cfg(test)]
+    pub(crate) fn headless() -> QuadGl {
+        // geometry only compares texture ids, it never uses them
+        let texture = miniquad::TextureId::from_raw_id(miniquad::RawId::OpenGl(0));
+
+        QuadGl::from_parts(
+            PipelinesStorage {
+                pipelines: Default::default(),
+                pipelines_amount: 0,
+            },
+            None,
+            texture,
+            texture,
+        )
+    }
+
+    /// The vertices and indices queued in each draw call so far.
+    #[cfg(test)]
+    pub(crate) fn queued_geometry(&self) -> Vec<(&[Vertex], &[u16])> {
+        self.draw_calls[0..self.draw_calls_count]
+            .iter()
+            .map(|dc| (dc.vertices(), dc.indices()))
+            .collect()
+    }
+
+    fn from_parts(
+        pipelines: PipelinesStorage,
+        snapshotter: Option<MagicSnapshotter>,
+        white_texture: miniquad::TextureId,
+        red_texture: miniquad::TextureId,
+    ) -> QuadGl {
         QuadGl {
-            pipelines: PipelinesStorage::new(ctx),
+            pipelines,
             state: GlState {
                 clip: None,
                 viewport: None,
                 texture: None,
                 model_stack: vec![glam::Mat4::IDENTITY],
+                tint_stack: vec![Color::WHITE],
                 draw_mode: DrawMode::Triangles,
                 pipeline: None,
                 break_batching: false,
                 depth_test_enable: false,
-                snapshotter: MagicSnapshotter::new(ctx),
+                depth: None,
+                premultiplied_alpha: false,
+                wireframe: false,
+                snapshotter,
                 render_pass: None,
                 capture: false,
             },
@@ -634,6 +821,8 @@ impl QuadGl {
             draw_calls_bindings: Vec::with_capacity(200),
             draw_calls_count: 0,
             start_time: miniquad::date::now(),
+            stats: FrameStats::default(),
+            srgb: false,
 
             white_texture: white_texture,
             red_texture: red_texture,
@@ -712,6 +901,9 @@ impl QuadGl {
         self.state.clip = None;
         self.state.texture = None;
         self.state.model_stack = vec![glam::Mat4::IDENTITY];
+        self.state.tint_stack = vec![Color::WHITE];
+        self.state.depth_test_enable = false;
+        self.state.depth = None;
 
         self.draw_calls_count = 0;
     }
@@ -763,7 +955,9 @@ impl QuadGl {
 
             #[cfg(not)]
             if pipeline.wants_screen_texture {
-                self.state.snapshotter.snapshot(ctx, dc.render_pass);
+                if let Some(snapshotter) = &mut self.state.snapshotter {
+                    snapshotter.snapshot(ctx, dc.render_pass);
+                }
             }
 
             if let Some(render_pass) = dc.render_pass {
@@ -782,8 +976,9 @@ impl QuadGl {
             bindings.images[1] = self
                 .state
                 .snapshotter
-                .screen_texture
-                .unwrap_or_else(|| white_texture);
+                .as_ref()
+                .and_then(|snapshotter| snapshotter.screen_texture)
+                .unwrap_or(white_texture);
             bindings
                 .images
                 .resize(2 + pipeline.textures.len(), white_texture);
@@ -815,6 +1010,23 @@ impl QuadGl {
             pipeline.set_uniform("Projection", projection);
             pipeline.set_uniform("Model", dc.model);
             pipeline.set_uniform("_Time", time);
+            // offscreen passes draw to plain RGBA textures, which hold sRGB values like
+            // any other image, so only the window's framebuffer is drawn in linear light
+            let srgb = self.srgb && dc.render_pass.is_none();
+            pipeline.set_uniform("_Srgb", if srgb { 1f32 } else { 0f32 });
+            pipeline.set_uniform("_Premultiplied", if dc.premultiplied_alpha { 1f32 } else { 0f32 });
+            // single channel textures, such as alpha font atlases, hold coverage only
+            let alpha_texture = dc.texture.is_some_and(|texture| {
+                ctx.texture_params(texture).format == miniquad::TextureFormat::Alpha
+            });
+            pipeline.set_uniform(
+                "_AlphaTexture",
+                match (alpha_texture, dc.premultiplied_alpha) {
+                    (false, _) => 0f32,
+                    (true, false) => 1f32,
+                    (true, true) => 2f32,
+                },
+            );
             ctx.apply_uniforms_from_bytes(
                 pipeline.uniforms_data.as_ptr(),
                 pipeline.uniforms_data.len(),
@@ -822,6 +1034,10 @@ impl QuadGl {
             ctx.draw(0, dc.indices_count as i32, 1);
             ctx.end_render_pass();
 
+            self.stats.draw_calls += 1;
+            self.stats.vertices += dc.vertices_count;
+            self.stats.indices += dc.indices_count;
+
             if dc.capture {
                 //telemetry::track_drawcall(&pipeline.pipeline, bindings, dc.indices_count);
             }
@@ -833,6 +1049,12 @@ impl QuadGl {
         self.draw_calls_count = 0;
     }
 
+    /// Totals for every draw call submitted since the last call, resetting
+    /// them to zero.
+    pub fn take_stats(&mut self) -> FrameStats {
+        std::mem::take(&mut self.stats)
+    }
+
     pub(crate) fn capture(&mut self, capture: bool) {
         self.state.capture = capture;
     }
@@ -862,6 +1084,48 @@ impl QuadGl {
         self.state.depth_test_enable = enable;
     }
 
+    pub fn get_depth(&self) -> Option<f32> {
+        self.state.depth
+    }
+
+    /// Overrides the z coordinate of subsequent geometry. With `None`, vertices
+    /// keep the z they were submitted with.
+    pub fn depth(&mut self, depth: Option<f32>) {
+        self.state.depth = depth;
+    }
+
+    pub fn is_wireframe(&self) -> bool {
+        self.state.wireframe
+    }
+
+    /// Draw the edges of subsequent triangles as lines instead of filling them,
+    /// to show how shapes are tessellated.
+    pub fn wireframe(&mut self, enable: bool) {
+        self.state.wireframe = enable;
+    }
+
+    /// Decode vertex colors and textures from sRGB in the shader, so that they
+    /// are blended in linear light. Only correct when the framebuffer encodes
+    /// its output back to sRGB.
+    pub fn set_srgb(&mut self, srgb: bool) {
+        self.srgb = srgb;
+    }
+
+    pub fn is_srgb(&self) -> bool {
+        self.srgb
+    }
+
+    pub fn is_premultiplied_alpha(&self) -> bool {
+        self.state.premultiplied_alpha
+    }
+
+    /// Blend subsequent geometry as premultiplied alpha (`One`, `OneMinusSourceAlpha`)
+    /// instead of straight alpha. Vertex colors and textures are expected to be
+    /// premultiplied as well. Has no effect while a custom pipeline is set.
+    pub fn premultiplied_alpha(&mut self, enable: bool) {
+        self.state.premultiplied_alpha = enable;
+    }
+
     pub fn texture_none(
         &mut self, 
         ) 
@@ -888,13 +1152,8 @@ impl QuadGl {
 
     pub fn get_viewport(&self) -> (i32, i32, i32, i32) {
         let (screen_width, screen_height) = miniquad::window::screen_size();
-        let scale = miniquad::window::dpi_scale();
-        self.state.viewport.unwrap_or((
-            0,
-            0,
-            (screen_width / scale) as _,
-            (screen_height / scale) as _,
-        ))
+        let size = DpiScaler::current().to_logical_size(screen_width, screen_height);
+        self.state.viewport.unwrap_or((0, 0, size.x as _, size.y as _))
     }
 
     pub fn push_model_matrix(&mut self, matrix: glam::Mat4) {
@@ -907,6 +1166,45 @@ impl QuadGl {
         }
     }
 
+    /// Multiply the color of all subsequent geometry by `tint`, in addition to
+    /// any tints already pushed.
+    pub fn push_tint(&mut self, tint: Color) {
+        let top = self.state.tint();
+        self.state.tint_stack.push(Color::from_rgba(
+            top.r() * tint.r(),
+            top.g() * tint.g(),
+            top.b() * tint.b(),
+            top.a() * tint.a(),
+        ));
+    }
+
+    pub fn pop_tint(&mut self) {
+        if self.state.tint_stack.len() > 1 {
+            self.state.tint_stack.pop();
+        }
+    }
+
+    /// The combination of every model matrix currently pushed.
+    pub fn get_active_model_matrix(&self) -> glam::Mat4 {
+        self.state.model()
+    }
+
+    pub fn get_pipeline(&self) -> Option<GlPipeline> {
+        self.state.pipeline
+    }
+
+    /// Pipeline for text from signed distance field fonts. Its "Smoothing"
+    /// uniform is the distance value over which glyph edges are antialiased.
+    pub fn sdf_text_pipeline(&self) -> GlPipeline {
+        PipelinesStorage::SDF_TEXT_PIPELINE
+    }
+
+    /// Pipeline for triangles without blending, which overwrites the target's
+    /// color and alpha rather than drawing over them.
+    pub fn replace_pipeline(&self) -> GlPipeline {
+        PipelinesStorage::TRIANGLES_REPLACE_PIPELINE
+    }
+
     pub fn pipeline(&mut self, pipeline: Option<GlPipeline>) {
         if self.state.pipeline == pipeline {
             return;
@@ -920,7 +1218,65 @@ impl QuadGl {
         self.state.draw_mode = mode;
     }
 
+    /// Forces the next "geometry" call to start a new draw call, even if it
+    /// could have been merged into the current one.
+    pub fn break_batching(&mut self) {
+        self.state.break_batching = true;
+    }
+
+    /// Like `geometry`, but splits meshes too large for a single draw call into
+    /// several, rather than clamping them. Every index must be in range for
+    /// `vertices`.
+    pub fn geometry_split(&mut self, vertices: &[Vertex], indices: &[u16]) {
+        if vertices.len() <= self.max_vertices && indices.len() <= self.max_indices {
+            self.geometry(vertices, indices);
+            return;
+        }
+
+        let triangles_per_call = self.max_indices.min(self.max_vertices) / 3;
+        let mut local_vertices = Vec::with_capacity(triangles_per_call * 3);
+        let mut local_indices = Vec::with_capacity(triangles_per_call * 3);
+        let mut remap = HashMap::new();
+
+        for triangles in indices.chunks(triangles_per_call * 3) {
+            local_vertices.clear();
+            local_indices.clear();
+            remap.clear();
+
+            for index in triangles {
+                let local = *remap.entry(*index).or_insert_with(|| {
+                    local_vertices.push(vertices[*index as usize]);
+                    (local_vertices.len() - 1) as u16
+                });
+                local_indices.push(local);
+            }
+
+            self.geometry(&local_vertices, &local_indices);
+        }
+    }
+
+    /// Queue geometry for drawing with the current state. Empty geometry is ignored, and
+    /// geometry with an index past the end of `vertices` is skipped with a warning.
     pub fn geometry(&mut self, vertices: &[impl Into<VertexInterop> + Copy], indices: &[u16]) {
+        if self.state.wireframe
+            && self.state.draw_mode == DrawMode::Triangles
+            && self.state.pipeline.is_none()
+        {
+            let mut edges = Vec::with_capacity(indices.len() * 2);
+            for triangle in indices.chunks_exact(3) {
+                edges.extend_from_slice(&[
+                    triangle[0], triangle[1],
+                    triangle[1], triangle[2],
+                    triangle[2], triangle[0],
+                ]);
+            }
+
+            self.state.draw_mode = DrawMode::Lines;
+            self.geometry(vertices, &edges);
+            self.state.draw_mode = DrawMode::Triangles;
+            return;
+        }
+
         if vertices.len() >= self.max_vertices || indices.len() >= self.max_indices {
             //warn!("geometry() exceeded max drawcall size, clamping");
         }
@@ -928,9 +1284,19 @@ impl QuadGl {
         let vertices = &vertices[0..self.max_vertices.min(vertices.len())];
         let indices = &indices[0..self.max_indices.min(indices.len())];
 
+        if vertices.is_empty() || indices.is_empty() {
+            return;
+        }
+        // an index past the end would read whatever was left in the draw call's
+        // vertex buffer by earlier geometry
+        if indices.iter().any(|index| *index as usize >= vertices.len()) {
+            ::log::warn!("geometry() given an index past the end of its vertices, skipping");
+            return;
+        }
+
         let pip = self.state.pipeline.unwrap_or(
             self.pipelines
-                .get(self.state.draw_mode, self.state.depth_test_enable),
+                .get(self.state.draw_mode, self.state.depth_test_enable, self.state.premultiplied_alpha),
         );
 
         let previous_dc_ix = if self.draw_calls_count == 0 {
@@ -951,6 +1317,7 @@ impl QuadGl {
                 || draw_call.vertices_count >= self.max_vertices - vertices.len()
                 || draw_call.indices_count >= self.max_indices - indices.len()
                 || draw_call.capture != self.state.capture
+                || draw_call.premultiplied_alpha != self.state.premultiplied_alpha
                 || self.state.break_batching
         }) {
             let uniforms = self.state.pipeline.map_or(None, |pipeline| {
@@ -984,14 +1351,34 @@ impl QuadGl {
             self.draw_calls[self.draw_calls_count].pipeline = pip;
             self.draw_calls[self.draw_calls_count].render_pass = self.state.render_pass;
             self.draw_calls[self.draw_calls_count].capture = self.state.capture;
+            self.draw_calls[self.draw_calls_count].premultiplied_alpha =
+                self.state.premultiplied_alpha;
 
             self.draw_calls_count += 1;
             self.state.break_batching = false;
         };
         let dc = &mut self.draw_calls[self.draw_calls_count - 1];
 
+        let tint = self.state.tint();
+        // premultiplied colors fade by scaling every component, not just alpha
+        let tint = if self.state.premultiplied_alpha {
+            tint.premultiplied()
+        } else {
+            tint
+        };
+        let tint = [tint.r(), tint.g(), tint.b(), tint.a()];
+
         for i in 0..vertices.len() {
-            dc.vertices[dc.vertices_count + i] = vertices[i].into().into();
+            let mut vertex: Vertex = vertices[i].into().into();
+            if let Some(depth) = self.state.depth {
+                vertex.pos[2] = depth;
+            }
+            if tint != [1.; 4] {
+                for (component, tint) in vertex.color.iter_mut().zip(tint) {
+                    *component = (*component as f32 * tint).round() as u8;
+                }
+            }
+            dc.vertices[dc.vertices_count + i] = vertex;
         }
 
         for i in 0..indices.len() {
@@ -1081,10 +1468,27 @@ mod shader {
 
     uniform mat4 Model;
     uniform mat4 Projection;
+    uniform mediump float _Srgb;
+    uniform mediump float _Premultiplied;
+
+    vec3 srgb_to_linear(vec3 c) {
+        return mix(c / 12.92, pow((c + 0.055) / 1.055, vec3(2.4)), step(0.04045, c));
+    }
+
+    // premultiplied colors are converted unpremultiplied, then premultiplied again
+    vec3 decode(vec4 c) {
+        if (_Premultiplied > 0.5) {
+            return c.a > 0.0 ? srgb_to_linear(c.rgb / c.a) * c.a : c.rgb;
+        }
+        return srgb_to_linear(c.rgb);
+    }
 
     void main() {
         gl_Position = Projection * Model * vec4(position, 1);
         color = color0 / 255.0;
+        if (_Srgb > 0.5) {
+            color.rgb = decode(color);
+        }
         uv = texcoord;
     }"#;
 
@@ -1093,9 +1497,34 @@ mod shader {
     varying lowp vec2 uv;
 
     uniform sampler2D Texture;
+    uniform mediump float _Srgb;
+    uniform mediump float _AlphaTexture;
+    uniform mediump float _Premultiplied;
+
+    mediump vec3 srgb_to_linear(mediump vec3 c) {
+        return mix(c / 12.92, pow((c + 0.055) / 1.055, vec3(2.4)), step(0.04045, c));
+    }
+
+    // premultiplied colors are converted unpremultiplied, then premultiplied again
+    mediump vec3 decode(mediump vec4 c) {
+        if (_Premultiplied > 0.5) {
+            return c.a > 0.0 ? srgb_to_linear(c.rgb / c.a) * c.a : c.rgb;
+        }
+        return srgb_to_linear(c.rgb);
+    }
 
     void main() {
-        gl_FragColor = color * texture2D(Texture, uv) ;
+        mediump vec4 texel = texture2D(Texture, uv);
+        // 1: white with the coverage as alpha, 2: the same, premultiplied
+        if (_AlphaTexture > 1.5) {
+            texel = vec4(texel.a);
+        } else if (_AlphaTexture > 0.5) {
+            texel = vec4(1.0, 1.0, 1.0, texel.a);
+        }
+        if (_Srgb > 0.5) {
+            texel.rgb = decode(texel);
+        }
+        gl_FragColor = color * texel;
     }"#;
 
     pub const METAL: &str = r#"
@@ -1138,11 +1567,91 @@ mod shader {
         return in.color * tex.sample(texSmplr, in.uv);
     }
     "#;
+    pub const SDF_FRAGMENT: &str = r#"#version 100
+    precision mediump float;
+
+    varying lowp vec4 color;
+    varying lowp vec2 uv;
+
+    uniform sampler2D Texture;
+    uniform float Smoothing;
+
+    void main() {
+        float distance = texture2D(Texture, uv).a;
+        float alpha = smoothstep(0.5 - Smoothing, 0.5 + Smoothing, distance);
+        gl_FragColor = vec4(color.rgb, color.a * alpha);
+    }"#;
+
+    pub const SDF_METAL: &str = r#"
+#include <metal_stdlib>
+    using namespace metal;
+
+    struct Uniforms
+    {
+        float4x4 Projection;
+        float4x4 Model;
+        float4 _Time;
+        float _Srgb;
+        float _AlphaTexture;
+        float _Premultiplied;
+        float Smoothing;
+    };
+
+    struct Vertex
+    {
+        float3 position    [[attribute(0)]];
+        float2 texcoord    [[attribute(1)]];
+        float4 color0      [[attribute(2)]];
+    };
+
+    struct RasterizerData
+    {
+        float4 position [[position]];
+        float4 color [[user(locn0)]];
+        float2 uv [[user(locn1)]];
+    };
+
+    vertex RasterizerData vertexShader(Vertex v [[stage_in]], constant Uniforms& uniforms [[buffer(0)]])
+    {
+        RasterizerData out;
+
+        out.position = uniforms.Projection * uniforms.Model * float4(v.position, 1);
+        out.color = v.color0 / 255.0;
+        out.uv = v.texcoord;
+
+        return out;
+    }
+
+    fragment float4 fragmentShader(RasterizerData in [[stage_in]], constant Uniforms& uniforms [[buffer(0)]], texture2d<float> tex [[texture(0)]], sampler texSmplr [[sampler(0)]])
+    {
+        float distance = tex.sample(texSmplr, in.uv).a;
+        float alpha = smoothstep(0.5 - uniforms.Smoothing, 0.5 + uniforms.Smoothing, distance);
+        return float4(in.color.rgb, in.color.a * alpha);
+    }
+    "#;
+
+    pub fn sdf_uniforms() -> Vec<(&'static str, UniformType)> {
+        vec![("Smoothing", UniformType::Float1)]
+    }
+
+    pub fn sdf_meta() -> ShaderMeta {
+        let mut meta = meta();
+        meta.uniforms.uniforms.extend(
+            sdf_uniforms()
+                .into_iter()
+                .map(|(name, kind)| UniformDesc::new(name, kind)),
+        );
+        meta
+    }
+
     pub fn uniforms() -> Vec<(&'static str, UniformType)> {
         vec![
             ("Projection", UniformType::Mat4),
             ("Model", UniformType::Mat4),
             ("_Time", UniformType::Float4),
+            ("_Srgb", UniformType::Float1),
+            ("_AlphaTexture", UniformType::Float1),
+            ("_Premultiplied", UniformType::Float1),
         ]
     }
 
@@ -17,7 +17,7 @@
 use num_traits::Zero;
 
 use crate::dimen::{Vec2, Vector2};
-use crate::numeric::{max, min, PrimitiveZero};
+use crate::numeric::{max, PrimitiveZero};
 
 /// A struct representing an axis-aligned rectangle. Two points are stored: the
 /// top left vertex, and the bottom right vertex.
@@ -117,6 +117,37 @@ impl<T: Copy> Rectangle<T>
         Vector2::new(self.top_left.x, self.bottom_right.y)
     }
 
+    /// Returns the four vertices of the rectangle, in clockwise order starting
+    /// from the top left: top left, top right, bottom right, bottom left.
+    #[inline]
+    #[must_use]
+    pub fn corners(&self) -> [Vector2<T>; 4]
+    {
+        [
+            self.top_left,
+            self.top_right(),
+            self.bottom_right,
+            self.bottom_left()
+        ]
+    }
+
+    /// Returns the four edges of the rectangle as `(start, end)` pairs of
+    /// vertices, in clockwise order: top, right, bottom, left. Each edge
+    /// starts at the vertex the previous one ended at.
+    #[inline]
+    #[must_use]
+    pub fn edges(&self) -> [(Vector2<T>, Vector2<T>); 4]
+    {
+        let [top_left, top_right, bottom_right, bottom_left] = self.corners();
+
+        [
+            (top_left, top_right),
+            (top_right, bottom_right),
+            (bottom_right, bottom_left),
+            (bottom_left, top_left)
+        ]
+    }
+
     /// Returns the x value of the left border
     #[inline]
     pub fn left(&self) -> T
@@ -188,6 +219,10 @@ impl<T: std::cmp::PartialOrd<T> + Copy> Rectangle<T>
     /// Returns true if the specified point is inside this rectangle. This is
     /// inclusive of the top and left coordinates, and exclusive of the bottom
     /// and right coordinates.
+    ///
+    /// This means adjacent rectangles never both contain the same point. To
+    /// also treat the bottom and right edges as inside, use
+    /// [Rectangle::contains_inclusive].
     #[inline]
     #[must_use]
     pub fn contains(&self, point: Vector2<T>) -> bool
@@ -197,6 +232,19 @@ impl<T: std::cmp::PartialOrd<T> + Copy> Rectangle<T>
             && point.x < self.bottom_right.x
             && point.y < self.bottom_right.y
     }
+
+    /// Returns true if the specified point is inside this rectangle. Unlike
+    /// [Rectangle::contains], this is inclusive of all four edges, matching
+    /// [RoundedRectangle::contains].
+    #[inline]
+    #[must_use]
+    pub fn contains_inclusive(&self, point: Vector2<T>) -> bool
+    {
+        point.x >= self.top_left.x
+            && point.y >= self.top_left.y
+            && point.x <= self.bottom_right.x
+            && point.y <= self.bottom_right.y
+    }
 }
 
 impl<T: std::cmp::PartialOrd + Copy> Rectangle<T>
@@ -211,14 +259,8 @@ impl<T: std::cmp::PartialOrd + Copy> Rectangle<T>
     pub fn intersect(&self, other: &Self) -> Option<Self>
     {
         let result = Self {
-            top_left: Vector2::new(
-                max(self.top_left.x, other.top_left.x),
-                max(self.top_left.y, other.top_left.y)
-            ),
-            bottom_right: Vector2::new(
-                min(self.bottom_right.x, other.bottom_right.x),
-                min(self.bottom_right.y, other.bottom_right.y)
-            )
+            top_left: self.top_left.max(&other.top_left),
+            bottom_right: self.bottom_right.min(&other.bottom_right)
         };
 
         if result.is_positive_area() {
@@ -286,6 +328,75 @@ where
     }
 }
 
+impl<T: num_traits::Num + num_traits::NumCast + Copy> Rectangle<T>
+{
+    /// Splits the rectangle into a grid of `columns` by `rows` equally sized
+    /// cells, returned in order from left to right along each row, and from
+    /// the top row to the bottom.
+    ///
+    /// For integer rectangles whose size doesn't divide exactly, the remainder
+    /// is spread across the cells, so that some are one unit larger than
+    /// others. The cells always exactly cover the rectangle, without gaps or
+    /// overlaps.
+    ///
+    /// No cells are returned if `columns` or `rows` is too large to be
+    /// represented by `T`.
+    pub fn subdivide(&self, columns: u32, rows: u32) -> impl Iterator<Item = Rectangle<T>>
+    {
+        let top_left = self.top_left;
+        let size = Vector2::new(
+            self.bottom_right.x - self.top_left.x,
+            self.bottom_right.y - self.top_left.y
+        );
+
+        // counts which can't be represented in `T` give no cells
+        let fits = |count: u32| <T as num_traits::NumCast>::from(count).is_some();
+        let (columns, rows) = if fits(columns) && fits(rows) {
+            (columns, rows)
+        } else {
+            (0, 0)
+        };
+
+        // integer types truncate a half to zero
+        let integer =
+            <T as num_traits::NumCast>::from(0.5).unwrap_or_else(T::zero) == T::zero();
+
+        // position of the boundary before cell `index` of `count`, worked out
+        // at a wider precision so `length * index` can't overflow `T`
+        let edge = move |start: T, length: T, index: u32, count: u32| {
+            let offset = if integer {
+                length
+                    .to_i128()
+                    .map(|length| length * index as i128 / count as i128)
+                    .and_then(<T as num_traits::NumCast>::from)
+            } else {
+                length
+                    .to_f64()
+                    .map(|length| length * index as f64 / count as f64)
+                    .and_then(<T as num_traits::NumCast>::from)
+            };
+
+            // the offset is never further than `length`, so always fits
+            start + offset.unwrap_or(length)
+        };
+
+        (0..rows).flat_map(move |row| {
+            (0..columns).map(move |column| {
+                Rectangle::new(
+                    Vector2::new(
+                        edge(top_left.x, size.x, column, columns),
+                        edge(top_left.y, size.y, row, rows)
+                    ),
+                    Vector2::new(
+                        edge(top_left.x, size.x, column + 1, columns),
+                        edge(top_left.y, size.y, row + 1, rows)
+                    )
+                )
+            })
+        })
+    }
+}
+
 impl<T: num_traits::AsPrimitive<f32>> Rectangle<T>
 {
     /// Returns a new rectangle where the coordinates have been cast to `f32`
@@ -310,11 +421,59 @@ impl<T: num_traits::AsPrimitive<f32> + Copy> Rectangle<T>
     }
 }
 
+impl From<crate::math::Rect> for Rectangle<f32>
+{
+    /// Converts from the position and size used by [crate::math::Rect].
+    #[inline]
+    fn from(rect: crate::math::Rect) -> Self
+    {
+        Rectangle::from_tuples((rect.x, rect.y), (rect.x + rect.w, rect.y + rect.h))
+    }
+}
+
+impl From<&Rectangle<f32>> for crate::math::Rect
+{
+    /// Converts to the position and size used by [crate::math::Rect].
+    #[inline]
+    fn from(rect: &Rectangle<f32>) -> Self
+    {
+        crate::math::Rect::new(rect.left(), rect.top(), rect.width(), rect.height())
+    }
+}
+
+impl From<Rectangle<f32>> for crate::math::Rect
+{
+    #[inline]
+    fn from(rect: Rectangle<f32>) -> Self
+    {
+        crate::math::Rect::from(&rect)
+    }
+}
+
+/// How the corners are drawn where two segments of a thick line meet.
+#[derive(Debug, Hash, PartialEq, Eq, Clone, Copy)]
+pub enum LineJoin
+{
+    /// The outer edges of the segments are extended until they meet in a
+    /// point. At very sharp corners, where the point would stick out more
+    /// than four times the line's thickness, a `Bevel` is used instead.
+    Miter,
+
+    /// The corner is rounded off with an arc, centered on the point where the
+    /// segments meet.
+    Round,
+
+    /// The corner is cut off with a straight edge between the outer edges of
+    /// the segments.
+    Bevel
+}
+
 /// A struct representing a polygon.
 #[derive(Debug, Clone)]
 pub struct Polygon
 {
-    pub(crate) triangles: Vec<[Vec2; 3]>
+    pub(crate) triangles: Vec<[Vec2; 3]>,
+    pub(crate) outline: Vec<Vec2>
 }
 
 impl Polygon
@@ -348,14 +507,107 @@ impl Polygon
             ])
         }
 
-        Polygon { triangles }
+        Polygon {
+            triangles,
+            outline: vertices.iter().map(|vertex| (*vertex).into()).collect()
+        }
+    }
+
+    /// Returns the points describing the outline of this polygon, in the
+    /// order they were originally provided.
+    #[inline]
+    #[must_use]
+    pub fn outline(&self) -> &[Vec2]
+    {
+        &self.outline
+    }
+
+    /// Returns true if the specified point is inside this polygon. Points
+    /// lying exactly on an edge are considered to be inside.
+    ///
+    /// This tests the point against each triangle of the polygon, so it works
+    /// for concave polygons too.
+    #[must_use]
+    pub fn contains<V: Into<Vec2>>(&self, point: V) -> bool
+    {
+        let point = point.into();
+
+        self.triangles.iter().any(|[a, b, c]| {
+            let edge = |from: &Vec2, to: &Vec2| {
+                (to.x - from.x) * (point.y - from.y) - (to.y - from.y) * (point.x - from.x)
+            };
+
+            let (ab, bc, ca) = (edge(a, b), edge(b, c), edge(c, a));
+
+            let has_negative = ab < 0.0 || bc < 0.0 || ca < 0.0;
+            let has_positive = ab > 0.0 || bc > 0.0 || ca > 0.0;
+
+            !(has_negative && has_positive)
+        })
     }
 }
 
 #[cfg(test)]
 mod test
 {
-    use crate::shape::URect;
+    use crate::dimen::{Vec2, Vector2};
+    use crate::shape::{IRect, Polygon, Rect, Rectangle, URect};
+
+    #[test]
+    pub fn test_math_rect_conversion()
+    {
+        let rect = Rect::from_tuples((10.0, 20.0), (40.0, 25.0));
+        let math_rect = crate::math::Rect::from(&rect);
+
+        assert_eq!(math_rect, crate::math::Rect::new(10.0, 20.0, 30.0, 5.0));
+        assert_eq!(Rect::from(math_rect), rect);
+    }
+
+    #[test]
+    pub fn test_subdivide()
+    {
+        let cells: Vec<URect> = URect::from_tuples((10, 20), (20, 24))
+            .subdivide(3, 2)
+            .collect();
+
+        assert_eq!(
+            cells,
+            vec![
+                URect::from_tuples((10, 20), (13, 22)),
+                URect::from_tuples((13, 20), (16, 22)),
+                URect::from_tuples((16, 20), (20, 22)),
+                URect::from_tuples((10, 22), (13, 24)),
+                URect::from_tuples((13, 22), (16, 24)),
+                URect::from_tuples((16, 22), (20, 24)),
+            ]
+        );
+
+        let cells: Vec<Rect> = Rect::from_tuples((0.0, 0.0), (1.0, 1.0))
+            .subdivide(2, 1)
+            .collect();
+
+        assert_eq!(
+            cells,
+            vec![
+                Rect::from_tuples((0.0, 0.0), (0.5, 1.0)),
+                Rect::from_tuples((0.5, 0.0), (1.0, 1.0)),
+            ]
+        );
+
+        assert_eq!(URect::from_tuples((0, 0), (10, 10)).subdivide(0, 4).count(), 0);
+
+        // `width * column` is past i32::MAX
+        let cells: Vec<IRect> = IRect::from_tuples((0, 0), (100_000, 1))
+            .subdivide(30_000, 1)
+            .collect();
+        assert_eq!(cells[29_999], IRect::from_tuples((99_996, 0), (100_000, 1)));
+        assert!(cells.windows(2).all(|pair| pair[0].bottom_right.x == pair[1].top_left.x));
+
+        // 300 doesn't fit in a u8
+        let small = Rectangle::<u8>::from_tuples((0, 0), (200, 10));
+        assert_eq!(small.subdivide(300, 1).count(), 0);
+        assert_eq!(small.subdivide(200, 1).last(), Some(Rectangle::from_tuples((199, 0), (200, 10))));
+    }
 
     #[test]
     pub fn test_intersect_1()
@@ -389,6 +641,85 @@ mod test
 
         assert_eq!(None, r1.intersect(&r2));
     }
+
+    #[test]
+    pub fn test_contains_inclusive()
+    {
+        let r = URect::from_tuples((100, 100), (200, 200));
+
+        assert!(r.contains(Vector2::new(100, 100)));
+        assert!(!r.contains(Vector2::new(200, 150)));
+        assert!(!r.contains(Vector2::new(150, 200)));
+
+        assert!(r.contains_inclusive(Vector2::new(100, 100)));
+        assert!(r.contains_inclusive(Vector2::new(200, 150)));
+        assert!(r.contains_inclusive(Vector2::new(200, 200)));
+        assert!(!r.contains_inclusive(Vector2::new(201, 150)));
+    }
+
+    #[test]
+    pub fn test_corners_and_edges()
+    {
+        let r = URect::from_tuples((100, 100), (200, 300));
+
+        assert_eq!(
+            [
+                Vector2::new(100, 100),
+                Vector2::new(200, 100),
+                Vector2::new(200, 300),
+                Vector2::new(100, 300)
+            ],
+            r.corners()
+        );
+
+        assert_eq!(
+            (Vector2::new(200, 100), Vector2::new(200, 300)),
+            r.edges()[1]
+        );
+        assert_eq!(
+            (Vector2::new(100, 300), Vector2::new(100, 100)),
+            r.edges()[3]
+        );
+    }
+
+    #[test]
+    pub fn test_polygon_outline()
+    {
+        let points = [(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)];
+        let polygon = Polygon::new(&points);
+
+        assert_eq!(2, polygon.triangles.len());
+        assert_eq!(
+            &[
+                Vec2::new(0.0, 0.0),
+                Vec2::new(10.0, 0.0),
+                Vec2::new(10.0, 10.0),
+                Vec2::new(0.0, 10.0)
+            ],
+            polygon.outline()
+        );
+    }
+
+    #[test]
+    pub fn test_polygon_contains()
+    {
+        // concave "L" shape
+        let polygon = Polygon::new(&[
+            (0.0, 0.0),
+            (10.0, 0.0),
+            (10.0, 5.0),
+            (5.0, 5.0),
+            (5.0, 10.0),
+            (0.0, 10.0)
+        ]);
+
+        assert!(polygon.contains((2.0, 2.0)));
+        assert!(polygon.contains((8.0, 2.0)));
+        assert!(polygon.contains((2.0, 8.0)));
+        assert!(polygon.contains((10.0, 0.0)));
+        assert!(!polygon.contains((8.0, 8.0)));
+        assert!(!polygon.contains((-1.0, 2.0)));
+    }
 }
 
 ///////////////////////////////////
@@ -417,6 +748,9 @@ pub type RoundRect = RoundedRectangle<f32>;
 /// A struct representing an axis-aligned rounded rectangle. Two points and a
 /// value of type 'T' are stored: the top left vertex, the bottom right vertex
 /// and the radius of the rounded corners.
+///
+/// Rounded rectangles can be drawn using
+/// [crate::Graphics2D::draw_rounded_rectangle].
 #[derive(Debug, PartialEq, Eq, Clone)]
 #[repr(C)]
 pub struct RoundedRectangle<T = f32>
@@ -591,11 +925,11 @@ where
     #[must_use]
     pub fn contains(&self, point: Vector2<T>) -> bool
     {
-        if !self.rect.contains(point) {
+        if !self.rect.contains_inclusive(point) {
             return false;
         }
         let inner = self.inner();
-        if inner.contains(point) {
+        if inner.contains_inclusive(point) {
             return true;
         }
 
@@ -5,6 +5,7 @@ use crate::quad_gl::QuadGl;
 
 use crate::quad_gl::{DrawMode, Vertex};
 use crate::math::Rect;
+use crate::shape::LineJoin;
 use glam::{vec2, vec3, vec4, Mat4, Vec2};
 
 /// Draws a solid triangle between points `v1`, `v2`, and `v3` with a given `color`.
@@ -22,6 +23,22 @@ pub fn draw_triangle(gl: &mut QuadGl, v1: Vec2, v2: Vec2, v3: Vec2, color: Color
     gl.geometry(&vertices, &indices);
 }
 
+/// Draws a solid triangle between points `v1`, `v2`, and `v3`, blending between `colors`, one
+/// for each point in the same order.
+pub fn draw_triangle_three_color(gl: &mut QuadGl, v1: Vec2, v2: Vec2, v3: Vec2, colors: [Color; 3]) {
+    let vertices = [
+        Vertex::new(v1.x, v1.y, 0., 0., 0., colors[0]),
+        Vertex::new(v2.x, v2.y, 0., 0., 0., colors[1]),
+        Vertex::new(v3.x, v3.y, 0., 0., 0., colors[2]),
+    ];
+
+    let indices: [u16; 3] = [0, 1, 2];
+
+    gl.texture_none();
+    gl.draw_mode(DrawMode::Triangles);
+    gl.geometry(&vertices, &indices);
+}
+
 /// Draws a triangle outline between points `v1`, `v2`, and `v3` with a given line `thickness` and `color`.
 pub fn draw_triangle_lines(gl: &mut QuadGl, v1: Vec2, v2: Vec2, v3: Vec2, thickness: f32, color: Color) {
     draw_line(gl, v1.x, v1.y, v2.x, v2.y, thickness, color);
@@ -47,7 +64,9 @@ pub fn draw_triangle_lines(gl: &mut QuadGl, v1: Vec2, v2: Vec2, v3: Vec2, thickn
      pub color: Color,
      /// If greater than 0.0, draws a rectangle outline with given `line_thickness`
      pub line_thickness: f32,
-     /// Horizontal and vertical skew proportions
+     /// Horizontal and vertical skew proportions.
+     /// Skew is always applied about the rectangle's center, before `rotation`
+     /// is applied about `pivot`.
      pub skew: Vec2,
      /// Radius of rectangle's corners
      pub border_radius: f32,
@@ -322,6 +341,215 @@ pub fn draw_rectangle(gl: &mut QuadGl, x: f32, y: f32, w: f32, h: f32, color: Co
     gl.geometry(&vertices, &indices);
 }
 
+/// Draws a rectangle with its top-left corner at `[x, y]` with size `[w, h]`, interpolating
+/// between the given corner colors, in order: `[top_left, top_right, bottom_right, bottom_left]`.
+///
+/// Unlike the `gradient` of [DrawRectangleParams2], there is no center vertex, so the colors
+/// blend directly across the two triangles.
+pub fn draw_rectangle_vertex_colors(
+    gl: &mut QuadGl,
+    x: f32,
+    y: f32,
+    w: f32,
+    h: f32,
+    colors: [Color; 4],
+) {
+    #[rustfmt::skip]
+    let vertices = [
+        Vertex::new(x    , y    , 0., 0.0, 0.0, colors[0]),
+        Vertex::new(x + w, y    , 0., 1.0, 0.0, colors[1]),
+        Vertex::new(x + w, y + h, 0., 1.0, 1.0, colors[2]),
+        Vertex::new(x    , y + h, 0., 0.0, 1.0, colors[3]),
+    ];
+    let indices: [u16; 6] = [0, 1, 2, 0, 2, 3];
+
+    gl.texture_none();
+    gl.draw_mode(DrawMode::Triangles);
+    gl.geometry(&vertices, &indices);
+}
+
+/// Fills `rect` with a checkerboard of `cell_size` squares, alternating between `color_a`,
+/// starting at the top left, and `color_b`. Cells along the right and bottom edges are cut
+/// off to fit.
+pub fn draw_checkerboard(gl: &mut QuadGl, rect: Rect, cell_size: f32, color_a: Color, color_b: Color) {
+    let Rect { x, y, w, h } = rect;
+
+    // keep each batch of geometry within the draw call index limit
+    const MAX_CELLS: usize = 800;
+
+    if w <= 0. || h <= 0. || cell_size <= 0. {
+        return;
+    }
+
+    let columns = (w / cell_size).ceil() as usize;
+    let rows = (h / cell_size).ceil() as usize;
+
+    gl.texture_none();
+    gl.draw_mode(DrawMode::Triangles);
+
+    let mut vertices = Vec::with_capacity(MAX_CELLS * 4);
+    let mut indices = Vec::with_capacity(MAX_CELLS * 6);
+
+    for row in 0..rows {
+        for column in 0..columns {
+            let left = x + column as f32 * cell_size;
+            let top = y + row as f32 * cell_size;
+            let right = (left + cell_size).min(x + w);
+            let bottom = (top + cell_size).min(y + h);
+            let color = if (row + column) % 2 == 0 { color_a } else { color_b };
+
+            let base = vertices.len() as u16;
+            vertices.extend_from_slice(&[
+                Vertex::new(left, top, 0., 0., 0., color),
+                Vertex::new(right, top, 0., 0., 0., color),
+                Vertex::new(right, bottom, 0., 0., 0., color),
+                Vertex::new(left, bottom, 0., 0., 0., color),
+            ]);
+            indices.extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3]);
+
+            if indices.len() == MAX_CELLS * 6 {
+                gl.geometry(&vertices, &indices);
+                vertices.clear();
+                indices.clear();
+            }
+        }
+    }
+
+    if !indices.is_empty() {
+        gl.geometry(&vertices, &indices);
+    }
+}
+
+/// Draws the lines of a grid of `cell_size` cells covering `bounds`, starting from its top left
+/// corner and including lines along every edge. Each line is `thickness` pixels thick, centered
+/// on the cell boundary. With `major_every` of `Some(n)`, every `n`th line, counting from the
+/// top and left edges, is drawn twice as thick.
+///
+/// Cells smaller than a pixel only have every few lines drawn, keeping them at least a pixel
+/// apart. Lines cross over each other, so translucent lines are blended twice where they meet.
+pub fn draw_grid(
+    gl: &mut QuadGl,
+    bounds: Rect,
+    cell_size: Vec2,
+    thickness: f32,
+    major_every: Option<u32>,
+    color: Color,
+) {
+    // keep each batch of geometry within the draw call index limit
+    const MAX_LINES: usize = 800;
+
+    if bounds.w <= 0. || bounds.h <= 0. || cell_size.x <= 0. || cell_size.y <= 0. {
+        return;
+    }
+
+    let line_thickness = |index: usize| match major_every {
+        Some(n) if n > 0 && index.is_multiple_of(n as usize) => thickness * 2.,
+        _ => thickness,
+    };
+
+    let (right, bottom) = (bounds.right(), bounds.bottom());
+
+    let vertical = grid_lines(bounds.x, bounds.w, cell_size.x).map(|(column, x)| {
+        let half = line_thickness(column) * 0.5;
+        (x - half, bounds.y, x + half, bottom)
+    });
+    let horizontal = grid_lines(bounds.y, bounds.h, cell_size.y).map(|(row, y)| {
+        let half = line_thickness(row) * 0.5;
+        (bounds.x, y - half, right, y + half)
+    });
+
+    gl.texture_none();
+    gl.draw_mode(DrawMode::Triangles);
+
+    let mut vertices = Vec::with_capacity(MAX_LINES * 4);
+    let mut indices = Vec::with_capacity(MAX_LINES * 6);
+
+    for (left, top, right, bottom) in vertical.chain(horizontal) {
+        let base = vertices.len() as u16;
+        vertices.extend_from_slice(&[
+            Vertex::new(left, top, 0., 0., 0., color),
+            Vertex::new(right, top, 0., 0., 0., color),
+            Vertex::new(right, bottom, 0., 0., 0., color),
+            Vertex::new(left, bottom, 0., 0., 0., color),
+        ]);
+        indices.extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3]);
+
+        if indices.len() == MAX_LINES * 6 {
+            gl.geometry(&vertices, &indices);
+            vertices.clear();
+            indices.clear();
+        }
+    }
+
+    if !indices.is_empty() {
+        gl.geometry(&vertices, &indices);
+    }
+}
+
+/// The index and position of each line of a grid along one axis, from `start` to the far edge
+/// `length` away.
+fn grid_lines(start: f32, length: f32, cell_size: f32) -> impl Iterator<Item = (usize, f32)> {
+    // lines closer together than a pixel would blend into a solid fill, and could number in
+    // the millions
+    let stride = (1. / cell_size).ceil().max(1.) as usize;
+    let cells = (length / cell_size).ceil() as usize;
+
+    // a cell count rounded up past a whole number puts the last line on the far edge already
+    let last = cells.saturating_sub(1) / stride * stride;
+    let edge_drawn = length - last as f32 * cell_size < cell_size * 1e-3;
+
+    (0..cells)
+        .step_by(stride)
+        .map(move |index| (index, start + index as f32 * cell_size))
+        .chain((!edge_drawn).then_some((cells, start + length)))
+}
+
+/// Draws a solid rectangle `rect`, filled with `color` and with an inset shadow: each edge is
+/// `shadow_color`, fading to `color` over `shadow_size` pixels inward. A negative width or
+/// height extends the rectangle left of or above its position.
+pub fn draw_rectangle_inner_shadow(
+    gl: &mut QuadGl,
+    rect: Rect,
+    color: Color,
+    shadow_color: Color,
+    shadow_size: f32,
+) {
+    let (x, w) = (rect.x.min(rect.x + rect.w), rect.w.abs());
+    let (y, h) = (rect.y.min(rect.y + rect.h), rect.h.abs());
+
+    // shadows from opposite edges meet in the middle rather than crossing
+    let inset_x = shadow_size.max(0.).min(w / 2.);
+    let inset_y = shadow_size.max(0.).min(h / 2.);
+
+    #[rustfmt::skip]
+    let vertices = [
+        // outer corners, clockwise from the top left
+        Vertex::new(x    , y    , 0., 0.0, 0.0, shadow_color),
+        Vertex::new(x + w, y    , 0., 1.0, 0.0, shadow_color),
+        Vertex::new(x + w, y + h, 0., 1.0, 1.0, shadow_color),
+        Vertex::new(x    , y + h, 0., 0.0, 1.0, shadow_color),
+        // inner corners
+        Vertex::new(x + inset_x    , y + inset_y    , 0., inset_x / w      , inset_y / h      , color),
+        Vertex::new(x + w - inset_x, y + inset_y    , 0., 1.0 - inset_x / w, inset_y / h      , color),
+        Vertex::new(x + w - inset_x, y + h - inset_y, 0., 1.0 - inset_x / w, 1.0 - inset_y / h, color),
+        Vertex::new(x + inset_x    , y + h - inset_y, 0., inset_x / w      , 1.0 - inset_y / h, color),
+    ];
+    #[rustfmt::skip]
+    let indices: [u16; 30] = [
+        // top, right, bottom and left edges
+        0, 1, 5, 0, 5, 4,
+        1, 2, 6, 1, 6, 5,
+        2, 3, 7, 2, 7, 6,
+        3, 0, 4, 3, 4, 7,
+        // inner fill
+        4, 5, 6, 4, 6, 7,
+    ];
+
+    gl.texture_none();
+    gl.draw_mode(DrawMode::Triangles);
+    gl.geometry(&vertices, &indices);
+}
+
 /// Draws a rectangle outline with its top-left corner at `[x, y]` with size `[w, h]` (width going to
 /// the right, height going down), with a given line `thickness` and `color`.
 pub fn draw_rectangle_lines(gl: &mut QuadGl, x: f32, y: f32, w: f32, h: f32, thickness: f32, color: Color) {
@@ -329,10 +557,10 @@ pub fn draw_rectangle_lines(gl: &mut QuadGl, x: f32, y: f32, w: f32, h: f32, thi
 
     #[rustfmt::skip]
     let vertices = [
-        Vertex::new(x    , y    , 0., 0.0, 1.0, color),
+        Vertex::new(x    , y    , 0., 0.0, 0.0, color),
         Vertex::new(x + w, y    , 0., 1.0, 0.0, color),
         Vertex::new(x + w, y + h, 0., 1.0, 1.0, color),
-        Vertex::new(x    , y + h, 0., 0.0, 0.0, color),
+        Vertex::new(x    , y + h, 0., 0.0, 1.0, color),
         //inner rectangle
         Vertex::new(x + t    , y + t    , 0., 0.0, 0.0, color),
         Vertex::new(x + w - t, y + t    , 0., 0.0, 0.0, color),
@@ -413,24 +641,46 @@ pub fn draw_hexagon(
     border_color: Color,
     fill_color: Color,
 ) {
-    let rotation = if vertical { 90. } else { 0. };
-    draw_poly(gl, x, y, 6, size, rotation, fill_color);
+    let rotation = if vertical { std::f32::consts::FRAC_PI_2 } else { 0. };
+    draw_poly(gl, vec2(x, y), 6, size, rotation, Antialiasing::NONE, fill_color);
     if border > 0. {
         draw_poly_lines(gl, x, y, 6, size, rotation, border, border_color);
     }
 }
 
-/// Draws a solid regular polygon centered at `[x, y]` with a given number of `sides`, `radius`,
-/// clockwise `rotation` (in degrees) and `color`.
-pub fn draw_poly(gl: &mut QuadGl, x: f32, y: f32, sides: u8, radius: f32, rotation: f32, color: Color) {
+/// How the edges of filled shapes are antialiased, for when multisampling isn't available.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Antialiasing {
+    /// When above zero, the edges fade out over this many pixels outside the shape
+    /// (see "draw_fill_feather").
+    pub feather: f32,
+}
+
+impl Antialiasing {
+    /// Hard edges, left to multisampling if any.
+    pub const NONE: Antialiasing = Antialiasing { feather: 0. };
+}
+
+/// Draws a solid regular polygon centered at `center` with a given number of `sides`, `radius`,
+/// clockwise `rotation` (in radians) and `color`, with its edges antialiased as given by
+/// `antialiasing`.
+pub fn draw_poly(
+    gl: &mut QuadGl,
+    center: Vec2,
+    sides: u8,
+    radius: f32,
+    rotation: f32,
+    antialiasing: Antialiasing,
+    color: Color,
+) {
+    let (x, y) = (center.x, center.y);
     let mut vertices = Vec::<Vertex>::with_capacity(sides as usize + 2);
     let mut indices = Vec::<u16>::with_capacity(sides as usize * 3);
 
-    let rot = rotation.to_radians();
     vertices.push(Vertex::new(x, y, 0., 0., 0., color));
     for i in 0..=sides {
-        let rx = (i as f32 / sides as f32 * std::f32::consts::PI * 2. + rot).cos();
-        let ry = (i as f32 / sides as f32 * std::f32::consts::PI * 2. + rot).sin();
+        let rx = (i as f32 / sides as f32 * std::f32::consts::PI * 2. + rotation).cos();
+        let ry = (i as f32 / sides as f32 * std::f32::consts::PI * 2. + rotation).sin();
 
         let vertex = Vertex::new(x + radius * rx, y + radius * ry, 0., rx, ry, color);
 
@@ -444,10 +694,14 @@ pub fn draw_poly(gl: &mut QuadGl, x: f32, y: f32, sides: u8, radius: f32, rotati
     gl.texture_none();
     gl.draw_mode(DrawMode::Triangles);
     gl.geometry(&vertices, &indices);
+
+    if antialiasing.feather > 0. {
+        draw_fill_feather(gl, &outline_of(&vertices[1..=sides as usize]), antialiasing.feather, color);
+    }
 }
 
 /// Draws a regular polygon outline centered at `[x, y]` with a given number of `sides`, `radius`,
-/// clockwise `rotation` (in degrees), line `thickness`, and `color`.
+/// clockwise `rotation` (in radians), line `thickness`, and `color`.
 pub fn draw_poly_lines(
     gl: &mut QuadGl,
     x: f32,
@@ -458,16 +712,14 @@ pub fn draw_poly_lines(
     thickness: f32,
     color: Color,
 ) {
-    let rot = rotation.to_radians();
-
     for i in 0..sides {
-        let rx = (i as f32 / sides as f32 * std::f32::consts::PI * 2. + rot).cos();
-        let ry = (i as f32 / sides as f32 * std::f32::consts::PI * 2. + rot).sin();
+        let rx = (i as f32 / sides as f32 * std::f32::consts::PI * 2. + rotation).cos();
+        let ry = (i as f32 / sides as f32 * std::f32::consts::PI * 2. + rotation).sin();
 
         let p0 = vec2(x + radius * rx, y + radius * ry);
 
-        let rx = ((i + 1) as f32 / sides as f32 * std::f32::consts::PI * 2. + rot).cos();
-        let ry = ((i + 1) as f32 / sides as f32 * std::f32::consts::PI * 2. + rot).sin();
+        let rx = ((i + 1) as f32 / sides as f32 * std::f32::consts::PI * 2. + rotation).cos();
+        let ry = ((i + 1) as f32 / sides as f32 * std::f32::consts::PI * 2. + rotation).sin();
 
         let p1 = vec2(x + radius * rx, y + radius * ry);
 
@@ -475,27 +727,69 @@ pub fn draw_poly_lines(
     }
 }
 
-/// Draws a solid circle centered at `[x, y]` with a given radius `r` and `color`.
-pub fn draw_circle(gl: &mut QuadGl, x: f32, y: f32, r: f32, color: Color) {
-    draw_poly(gl, x, y, 20, r, 0., color);
+/// Draws a solid circle centered at `center` with a given radius `r`, number of `sides` and
+/// `color`, with its edge antialiased like "draw_poly".
+pub fn draw_circle(gl: &mut QuadGl, center: Vec2, r: f32, sides: u8, antialiasing: Antialiasing, color: Color) {
+    draw_poly(gl, center, sides, r, 0., antialiasing, color);
 }
 
-/// Draws a circle outline centered at `[x, y]` with a given radius, line `thickness` and `color`.
-pub fn draw_circle_lines(gl: &mut QuadGl, x: f32, y: f32, r: f32, thickness: f32, color: Color) {
-    draw_poly_lines(gl, x, y, 20, r, 0., thickness, color);
+/// Draws a circle outline centered at `[x, y]` with a given radius, number of `sides`, line
+/// `thickness` and `color`.
+pub fn draw_circle_lines(gl: &mut QuadGl, x: f32, y: f32, r: f32, sides: u8, thickness: f32, color: Color) {
+    draw_poly_lines(gl, x, y, sides, r, 0., thickness, color);
 }
 
-/// Draws a solid ellipse centered at `[x, y]` with a given size `[w, h]`,
-/// clockwise `rotation` (in degrees) and `color`.
-pub fn draw_ellipse(gl: &mut QuadGl, x: f32, y: f32, w: f32, h: f32, rotation: f32, color: Color) {
-    let sides = 20;
+/// Draws a filled ring (annulus) centered at `center`, covering the area between
+/// `inner_radius` and `outer_radius`, with a given number of `segments` and `color`.
+pub fn draw_ring(
+    gl: &mut QuadGl,
+    center: Vec2,
+    inner_radius: f32,
+    outer_radius: f32,
+    segments: u8,
+    color: Color,
+) {
+    let (x, y) = (center.x, center.y);
+    let mut vertices = Vec::<Vertex>::with_capacity((segments as usize + 1) * 2);
+    let mut indices = Vec::<u16>::with_capacity(segments as usize * 6);
+
+    for i in 0..=segments {
+        let rx = (i as f32 / segments as f32 * std::f32::consts::PI * 2.).cos();
+        let ry = (i as f32 / segments as f32 * std::f32::consts::PI * 2.).sin();
+
+        vertices.push(Vertex::new(x + inner_radius * rx, y + inner_radius * ry, 0., rx, ry, color));
+        vertices.push(Vertex::new(x + outer_radius * rx, y + outer_radius * ry, 0., rx, ry, color));
+
+        if i != segments {
+            let inner = i as u16 * 2;
+            let outer = inner + 1;
+            indices.extend_from_slice(&[inner, outer, outer + 2, inner, outer + 2, inner + 2]);
+        }
+    }
+
+    gl.texture_none();
+    gl.draw_mode(DrawMode::Triangles);
+    gl.geometry(&vertices, &indices);
+}
 
+/// Draws a solid ellipse centered at `center` with a given `size` (its horizontal and vertical
+/// radii), number of `sides`, clockwise `rotation` (in radians) and `color`, with its edge
+/// antialiased like "draw_poly".
+pub fn draw_ellipse(
+    gl: &mut QuadGl,
+    center: Vec2,
+    size: Vec2,
+    sides: u8,
+    rotation: f32,
+    antialiasing: Antialiasing,
+    color: Color,
+) {
+    let (x, y, w, h) = (center.x, center.y, size.x, size.y);
     let mut vertices = Vec::<Vertex>::with_capacity(sides as usize + 2);
     let mut indices = Vec::<u16>::with_capacity(sides as usize * 3);
 
-    let rot = rotation.to_radians();
-    let sr = rot.sin();
-    let cr = rot.cos();
+    let sr = rotation.sin();
+    let cr = rotation.cos();
     vertices.push(Vertex::new(x, y, 0., 0., 0., color));
     for i in 0..=sides {
         let rx = (i as f32 / sides as f32 * std::f32::consts::PI * 2.).cos();
@@ -517,25 +811,85 @@ pub fn draw_ellipse(gl: &mut QuadGl, x: f32, y: f32, w: f32, h: f32, rotation: f
     gl.texture_none();
     gl.draw_mode(DrawMode::Triangles);
     gl.geometry(&vertices, &indices);
+
+    if antialiasing.feather > 0. {
+        draw_fill_feather(gl, &outline_of(&vertices[1..=sides as usize]), antialiasing.feather, color);
+    }
 }
 
-/// Draws an ellipse outline centered at `[x, y]` with a given size `[w, h]`,
-/// clockwise `rotation` (in degrees), line `thickness` and `color`.
+fn outline_of(vertices: &[Vertex]) -> Vec<Vec2> {
+    vertices.iter().map(|v| vec2(v.pos[0], v.pos[1])).collect()
+}
+
+/// Draws a ring `feather` pixels wide just outside the closed `outline` of a filled shape,
+/// fading from `color` at the outline to transparent, which antialiases the shape's edges
+/// without MSAA. The outline may be in either winding order.
+pub fn draw_fill_feather(gl: &mut QuadGl, outline: &[Vec2], feather: f32, color: Color) {
+    // keep each batch of geometry within the draw call index limit
+    const MAX_EDGES_PER_BATCH: usize = 800;
+    // limits how far the fringe sticks out at very sharp corners
+    const MAX_MITER: f32 = 4.;
+
+    let count = outline.len();
+    if count < 3 {
+        return;
+    }
+
+    let area: f32 = (0..count)
+        .map(|i| outline[i].perp_dot(outline[(i + 1) % count]))
+        .sum();
+    let side = if area > 0. { -1. } else { 1. };
+    let edge_normal = |from: Vec2, to: Vec2| (to - from).perp().normalize_or_zero() * side;
+
+    let offsets = (0..count)
+        .map(|i| {
+            let previous = edge_normal(outline[(i + count - 1) % count], outline[i]);
+            let next = edge_normal(outline[i], outline[(i + 1) % count]);
+            let miter = (previous + next).normalize_or_zero();
+            let scale = miter.dot(next).max(1. / MAX_MITER);
+            miter * (feather / scale)
+        })
+        .collect::<Vec<_>>();
+
+    let transparent = Color::from_rgba(color.r(), color.g(), color.b(), 0.);
+
+    gl.texture_none();
+    gl.draw_mode(DrawMode::Triangles);
+
+    let edges = (0..count).collect::<Vec<_>>();
+    for batch in edges.chunks(MAX_EDGES_PER_BATCH) {
+        let mut vertices = Vec::<Vertex>::with_capacity(batch.len() * 4);
+        let mut indices = Vec::<u16>::with_capacity(batch.len() * 6);
+
+        for &i in batch {
+            let base = vertices.len() as u16;
+            for j in [i, (i + 1) % count] {
+                let (point, outer) = (outline[j], outline[j] + offsets[j]);
+                vertices.push(Vertex::new(point.x, point.y, 0., 0., 0., color));
+                vertices.push(Vertex::new(outer.x, outer.y, 0., 0., 0., transparent));
+            }
+            indices.extend_from_slice(&[base, base + 1, base + 2, base + 2, base + 1, base + 3]);
+        }
+
+        gl.geometry(&vertices, &indices);
+    }
+}
+
+/// Draws an ellipse outline centered at `[x, y]` with a given size `[w, h]`, number of `sides`,
+/// clockwise `rotation` (in radians), line `thickness` and `color`.
 pub fn draw_ellipse_lines(
     gl: &mut QuadGl,
     x: f32,
     y: f32,
     w: f32,
     h: f32,
+    sides: u8,
     rotation: f32,
     thickness: f32,
     color: Color,
 ) {
-    let sides = 20;
-
-    let rot = rotation.to_radians();
-    let sr = rot.sin();
-    let cr = rot.cos();
+    let sr = rotation.sin();
+    let cr = rotation.cos();
     for i in 0..sides {
         let rx = (i as f32 / sides as f32 * std::f32::consts::PI * 2.).cos();
         let ry = (i as f32 / sides as f32 * std::f32::consts::PI * 2.).sin();
@@ -559,6 +913,178 @@ pub fn draw_ellipse_lines(
     }
 }
 
+/// Draws a line through each of `points` in turn, with a given `thickness` and `color`, and
+/// with corners drawn as described by `join`.
+/// When `closed` is true, the last point is also joined back to the first.
+pub fn draw_polyline(
+    gl: &mut QuadGl,
+    points: &[Vec2],
+    closed: bool,
+    thickness: f32,
+    color: Color,
+    join: LineJoin,
+) {
+    // repeated points have no direction to draw a segment or join along
+    let mut points = points.to_vec();
+    points.dedup();
+    if closed && points.len() > 1 && points[0] == points[points.len() - 1] {
+        points.pop();
+    }
+    if points.len() < 2 {
+        return;
+    }
+
+    let mut segments = points.windows(2).map(|segment| (segment[0], segment[1])).collect::<Vec<_>>();
+    if closed && points.len() > 2 {
+        segments.push((points[points.len() - 1], points[0]));
+    }
+
+    for (start, end) in &segments {
+        draw_line(gl, start.x, start.y, end.x, end.y, thickness, color);
+    }
+
+    let corners = segments.windows(2).map(|pair| (pair[0], pair[1]));
+    if closed && segments.len() > 2 {
+        let wrap = (segments[segments.len() - 1], segments[0]);
+        for (incoming, outgoing) in corners.chain(std::iter::once(wrap)) {
+            draw_line_join(gl, incoming, outgoing, thickness, color, join);
+        }
+    } else {
+        for (incoming, outgoing) in corners {
+            draw_line_join(gl, incoming, outgoing, thickness, color, join);
+        }
+    }
+}
+
+/// Fills the gap on the outside of the corner where segment `incoming` ends and `outgoing`
+/// begins, as drawn by `draw_line`.
+fn draw_line_join(
+    gl: &mut QuadGl,
+    incoming: (Vec2, Vec2),
+    outgoing: (Vec2, Vec2),
+    thickness: f32,
+    color: Color,
+    join: LineJoin,
+) {
+    // how far a miter may reach from the corner, in line thicknesses
+    const MITER_LIMIT: f32 = 4.;
+
+    let corner = incoming.1;
+    let half_thickness = thickness / 2.;
+    let incoming_direction = (incoming.1 - incoming.0).normalize();
+    let outgoing_direction = (outgoing.1 - outgoing.0).normalize();
+
+    // the outside of the corner is on the opposite side to the way the line turns
+    let turn = incoming_direction.perp_dot(outgoing_direction);
+    if turn.abs() < 1e-6 && incoming_direction.dot(outgoing_direction) > 0. {
+        return;
+    }
+    let outside = if turn > 0. { -1. } else { 1. };
+    let incoming_edge = incoming_direction.perp() * (half_thickness * outside);
+    let outgoing_edge = outgoing_direction.perp() * (half_thickness * outside);
+
+    let mut fan = vec![corner + incoming_edge];
+    match join {
+        LineJoin::Bevel => {}
+        LineJoin::Miter => {
+            let bisector = (incoming_edge + outgoing_edge).normalize_or_zero();
+            let cos_half_angle = bisector.dot(incoming_edge) / half_thickness;
+            if cos_half_angle > 0. && 1. / cos_half_angle <= MITER_LIMIT * 2. {
+                fan.push(corner + bisector * (half_thickness / cos_half_angle));
+            }
+        }
+        LineJoin::Round => {
+            // turn from one edge to the other through the direction of travel, which is the
+            // outside of the corner, even when the line doubles back on itself. The angle
+            // between the edges is signed, so only its size is used.
+            let angle = incoming_edge.angle_between(outgoing_edge).abs();
+            let sign = if incoming_edge.perp_dot(incoming_direction) > 0. { 1. } else { -1. };
+            let steps = (angle / (std::f32::consts::PI / 8.)).ceil().max(1.) as u16;
+            for step in 1..steps {
+                let rotation = Vec2::from_angle(sign * angle * step as f32 / steps as f32);
+                fan.push(corner + rotation.rotate(incoming_edge));
+            }
+        }
+    }
+    fan.push(corner + outgoing_edge);
+
+    let vertices = std::iter::once(corner)
+        .chain(fan)
+        .map(|p| Vertex::new(p.x, p.y, 0., 0., 0., color))
+        .collect::<Vec<_>>();
+    let indices = (1..vertices.len() as u16 - 1)
+        .flat_map(|i| [0, i, i + 1])
+        .collect::<Vec<_>>();
+
+    gl.texture_none();
+    gl.draw_mode(DrawMode::Triangles);
+    gl.geometry(&vertices, &indices);
+}
+
+/// Draws a smooth curve passing through each of `points` in turn, with a given `thickness` and
+/// `color`. The curve is a Catmull-Rom spline, drawn as `segments_per_span` straight lines
+/// between each pair of neighbouring points.
+pub fn draw_spline(
+    gl: &mut QuadGl,
+    points: &[Vec2],
+    thickness: f32,
+    color: Color,
+    segments_per_span: u8,
+) {
+    if points.len() < 2 {
+        return;
+    }
+    let segments_per_span = segments_per_span.max(1);
+
+    // the end points are repeated to give the first and last spans a tangent
+    let control = |i: isize| points[i.clamp(0, points.len() as isize - 1) as usize];
+
+    let mut curve = Vec::with_capacity((points.len() - 1) * segments_per_span as usize + 1);
+    curve.push(points[0]);
+    for span in 0..points.len() as isize - 1 {
+        let (p0, p1, p2, p3) = (control(span - 1), control(span), control(span + 1), control(span + 2));
+
+        for step in 1..=segments_per_span {
+            let t = step as f32 / segments_per_span as f32;
+            let (t2, t3) = (t * t, t * t * t);
+
+            curve.push(
+                0.5 * (2. * p1
+                    + (p2 - p0) * t
+                    + (2. * p0 - 5. * p1 + 4. * p2 - p3) * t2
+                    + (3. * p1 - p0 - 3. * p2 + p3) * t3),
+            );
+        }
+    }
+
+    draw_polyline(gl, &curve, false, thickness, color, LineJoin::Bevel);
+}
+
+/// Draws each `(start, end)` segment as a one pixel wide line, using GL line primitives rather
+/// than triangles. Lines can't be given a thickness, but are much cheaper to draw in bulk.
+pub fn draw_line_primitives(gl: &mut QuadGl, segments: &[(Vec2, Vec2)], color: Color) {
+    // keep each batch of geometry within the draw call index limit
+    const MAX_SEGMENTS: usize = 2000;
+
+    gl.texture_none();
+    gl.draw_mode(DrawMode::Lines);
+
+    for segments in segments.chunks(MAX_SEGMENTS) {
+        let vertices = segments
+            .iter()
+            .flat_map(|(start, end)| {
+                [
+                    Vertex::new(start.x, start.y, 0., 0., 0., color),
+                    Vertex::new(end.x, end.y, 0., 0., 0., color),
+                ]
+            })
+            .collect::<Vec<_>>();
+        let indices = (0..vertices.len() as u16).collect::<Vec<_>>();
+
+        gl.geometry(&vertices, &indices);
+    }
+}
+
 /// Draws a line between points `[x1, y1]` and `[x2, y2]` with a given `thickness` and `color`.
 pub fn draw_line(gl: &mut QuadGl, x1: f32, y1: f32, x2: f32, y2: f32, thickness: f32, color: Color) {
     let dx = x2 - x1;
@@ -588,3 +1114,251 @@ pub fn draw_line(gl: &mut QuadGl, x1: f32, y1: f32, x2: f32, y2: f32, thickness:
         &[0, 1, 2, 2, 1, 3],
     );
 }
+
+/// Draws a line like "draw_line", with its long edges faded out over `feather` pixels
+/// to antialias them without MSAA. The fade is centered on the line's edges, so the
+/// line keeps its apparent `thickness`.
+pub fn draw_line_ex(gl: &mut QuadGl, from: Vec2, to: Vec2, thickness: f32, feather: f32, color: Color) {
+    let (x1, y1, x2, y2) = (from.x, from.y, to.x, to.y);
+    let (dx, dy) = (x2 - x1, y2 - y1);
+    let length = (dx * dx + dy * dy).sqrt();
+    if length < f32::EPSILON {
+        return;
+    }
+    let (nx, ny) = (-dy / length, dx / length);
+
+    let inner = (thickness - feather).max(0.) * 0.5;
+    let outer = (thickness + feather) * 0.5;
+    let transparent = Color::from_rgba(color.r(), color.g(), color.b(), 0.);
+
+    // one strip across the line at each end: outer edge, solid core, outer edge
+    let mut vertices = Vec::<Vertex>::with_capacity(8);
+    for (x, y) in [(x1, y1), (x2, y2)] {
+        vertices.extend_from_slice(&[
+            Vertex::new(x + nx * outer, y + ny * outer, 0., 0., 0., transparent),
+            Vertex::new(x + nx * inner, y + ny * inner, 0., 0., 0., color),
+            Vertex::new(x - nx * inner, y - ny * inner, 0., 0., 0., color),
+            Vertex::new(x - nx * outer, y - ny * outer, 0., 0., 0., transparent),
+        ]);
+    }
+
+    gl.texture_none();
+    gl.draw_mode(DrawMode::Triangles);
+    gl.geometry(
+        &vertices,
+        &[
+            0, 1, 4, 4, 1, 5,
+            1, 2, 5, 5, 2, 6,
+            2, 3, 6, 6, 3, 7,
+        ],
+    );
+}
+
+/// Draws a line from `from` to `to` ending in a solid triangular arrowhead, which points at
+/// `to` and is `head_size` pixels both long and wide. The head shrinks to fit arrows shorter
+/// than `head_size`, and a zero length arrow draws nothing.
+pub fn draw_arrow(gl: &mut QuadGl, from: Vec2, to: Vec2, thickness: f32, head_size: f32, color: Color) {
+    let length = from.distance(to);
+    if length < f32::EPSILON {
+        return;
+    }
+
+    let direction = (to - from) / length;
+    let head_length = head_size.min(length);
+    // the line stops at the base of the head, so a thick line can't poke out past its sides
+    let base = to - direction * head_length;
+    let half_width = direction.perp() * (head_length * 0.5);
+
+    draw_line(gl, from.x, from.y, base.x, base.y, thickness, color);
+    draw_triangle(gl, to, base + half_width, base - half_width, color);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bounds(vertices: &[Vertex]) -> (Vec2, Vec2) {
+        let points = vertices.iter().map(|v| vec2(v.pos[0], v.pos[1]));
+        let min = points.clone().fold(Vec2::splat(f32::MAX), Vec2::min);
+        let max = points.fold(Vec2::splat(f32::MIN), Vec2::max);
+        (min, max)
+    }
+
+    // the bounds of the `line`th quad
+    fn bounds_of_line(vertices: &[Vertex], line: usize) -> (Vec2, Vec2) {
+        bounds(&vertices[line * 4..line * 4 + 4])
+    }
+
+    #[test]
+    fn test_checkerboard() {
+        let mut gl = QuadGl::headless();
+        draw_checkerboard(&mut gl, Rect::new(0., 0., 10., 10.), 4., Color::WHITE, Color::BLACK);
+
+        let geometry = gl.queued_geometry();
+        assert_eq!(geometry.len(), 1);
+
+        // 3x3 cells, the last row and column cut off at the edge
+        let (vertices, indices) = geometry[0];
+        assert_eq!(vertices.len(), 9 * 4);
+        assert_eq!(indices.len(), 9 * 6);
+        assert_eq!(bounds(vertices), (vec2(0., 0.), vec2(10., 10.)));
+    }
+
+    #[test]
+    fn test_grid() {
+        let mut gl = QuadGl::headless();
+        let bounds = Rect::new(0., 0., 25., 10.);
+        draw_grid(&mut gl, bounds, vec2(10., 10.), 1., Some(2), Color::WHITE);
+
+        // lines at x = 0, 10, 20 and the cut off edge at 25, and at y = 0 and 10
+        let geometry = gl.queued_geometry();
+        assert_eq!(geometry.len(), 1);
+        let (vertices, indices) = geometry[0];
+        assert_eq!(vertices.len(), 6 * 4);
+        assert_eq!(indices.len(), 6 * 6);
+
+        // the lines at x = 0 and x = 20 are major, and twice as thick
+        assert_eq!(bounds_of_line(vertices, 0), (vec2(-1., 0.), vec2(1., 10.)));
+        assert_eq!(bounds_of_line(vertices, 1), (vec2(9.5, 0.), vec2(10.5, 10.)));
+        assert_eq!(bounds_of_line(vertices, 2), (vec2(19., 0.), vec2(21., 10.)));
+        assert_eq!(bounds_of_line(vertices, 3), (vec2(24.5, 0.), vec2(25.5, 10.)));
+        assert_eq!(bounds_of_line(vertices, 5), (vec2(0., 9.5), vec2(25., 10.5)));
+
+        // 3.9 / 1.3 rounds up past 3, without adding a second line on the right edge
+        let mut gl = QuadGl::headless();
+        draw_grid(&mut gl, Rect::new(0., 0., 3.9, 1.3), vec2(1.3, 1.3), 0.1, None, Color::WHITE);
+        let (vertices, _) = gl.queued_geometry()[0];
+        assert_eq!(vertices.len(), (4 + 2) * 4);
+
+        // lines a tenth of a pixel apart are thinned out to a pixel apart
+        let mut gl = QuadGl::headless();
+        draw_grid(&mut gl, Rect::new(0., 0., 100., 100.), vec2(0.1, 0.1), 0.1, None, Color::WHITE);
+        let lines: usize = gl.queued_geometry().iter().map(|(vertices, _)| vertices.len() / 4).sum();
+        assert_eq!(lines, 101 * 2);
+    }
+
+    #[test]
+    fn test_rectangle_inner_shadow() {
+        let draw = |rect: Rect| {
+            let mut gl = QuadGl::headless();
+            draw_rectangle_inner_shadow(&mut gl, rect, Color::WHITE, Color::BLACK, 4.);
+            bounds(gl.queued_geometry()[0].0)
+        };
+
+        let expected = (vec2(10., 20.), vec2(16., 40.));
+        assert_eq!(draw(Rect::new(10., 20., 6., 20.)), expected);
+        // a negative size is measured back from the position
+        assert_eq!(draw(Rect::new(16., 40., -6., -20.)), expected);
+
+        // the shadow stops at the middle of the narrow side
+        let mut gl = QuadGl::headless();
+        draw_rectangle_inner_shadow(&mut gl, Rect::new(16., 40., -6., -20.), Color::WHITE, Color::BLACK, 4.);
+        let inner = &gl.queued_geometry()[0].0[4..];
+        assert_eq!(bounds(inner), (vec2(13., 24.), vec2(13., 36.)));
+    }
+
+    #[test]
+    fn test_round_join() {
+        let incoming = (vec2(0., 0.), vec2(10., 0.));
+
+        for end in [vec2(10., 10.), vec2(10., -10.)] {
+            let mut gl = QuadGl::headless();
+            draw_line_join(&mut gl, incoming, (incoming.1, end), 2., Color::WHITE, LineJoin::Round);
+
+            // a quarter turn is rounded in four steps, whichever way it turns: the corner,
+            // the two edges and three points between them
+            let geometry = gl.queued_geometry();
+            let (vertices, indices) = geometry[0];
+            assert_eq!(vertices.len(), 6, "turning towards {}", end);
+            assert_eq!(indices.len(), 4 * 3);
+
+            for vertex in &vertices[1..] {
+                let distance = vec2(vertex.pos[0], vertex.pos[1]).distance(incoming.1);
+                assert!((distance - 1.).abs() < 1e-4);
+            }
+        }
+    }
+
+    #[test]
+    fn test_fill_feather() {
+        let square = [vec2(0., 0.), vec2(10., 0.), vec2(10., 10.), vec2(0., 10.)];
+        let reversed = [square[3], square[2], square[1], square[0]];
+
+        for outline in [&square, &reversed] {
+            let mut gl = QuadGl::headless();
+            draw_fill_feather(&mut gl, outline, 1., Color::WHITE);
+
+            let geometry = gl.queued_geometry();
+            let (vertices, indices) = geometry[0];
+            assert_eq!(vertices.len(), 4 * 4);
+            assert_eq!(indices.len(), 4 * 6);
+
+            // the fringe lies outside the shape whichever way it winds, and the
+            // corners are mitered
+            let (min, max) = bounds(vertices);
+            assert!(min.abs_diff_eq(vec2(-1., -1.), 1e-4) && max.abs_diff_eq(vec2(11., 11.), 1e-4));
+            for vertex in vertices {
+                let inside = (0. ..=10.).contains(&vertex.pos[0]) && (0. ..=10.).contains(&vertex.pos[1]);
+                assert_eq!(vertex.color[3] == 255, inside);
+            }
+        }
+    }
+
+    #[test]
+    fn test_arrow() {
+        let mut gl = QuadGl::headless();
+        draw_arrow(&mut gl, vec2(0., 10.), vec2(20., 10.), 2., 6., Color::WHITE);
+
+        // the line and the head share a draw call
+        let geometry = gl.queued_geometry();
+        assert_eq!(geometry.len(), 1);
+        let (vertices, indices) = geometry[0];
+        assert_eq!(vertices.len(), 4 + 3);
+        assert_eq!(indices.len(), 6 + 3);
+        assert_eq!(bounds(vertices), (vec2(0., 7.), vec2(20., 13.)));
+        assert_eq!(bounds(&vertices[..4]), (vec2(0., 9.), vec2(14., 11.)));
+
+        // a head longer than the arrow shrinks to fit it
+        let mut gl = QuadGl::headless();
+        draw_arrow(&mut gl, vec2(0., 0.), vec2(0., 4.), 1., 10., Color::WHITE);
+        let geometry = gl.queued_geometry();
+        assert_eq!(geometry.len(), 1);
+        assert_eq!(bounds(geometry[0].0), (vec2(-2., 0.), vec2(2., 4.)));
+
+        let mut gl = QuadGl::headless();
+        draw_arrow(&mut gl, vec2(3., 3.), vec2(3., 3.), 2., 6., Color::WHITE);
+        assert!(gl.queued_geometry().is_empty());
+    }
+
+    #[test]
+    fn test_fill_feather_large_outline() {
+        let count = 1200;
+        let outline = (0..count)
+            .map(|i| Vec2::from_angle(i as f32 / count as f32 * std::f32::consts::TAU) * 100.)
+            .collect::<Vec<_>>();
+
+        let mut gl = QuadGl::headless();
+        draw_fill_feather(&mut gl, &outline, 1., Color::WHITE);
+
+        // every edge gets its fringe, split over several draw calls
+        let geometry = gl.queued_geometry();
+        assert!(geometry.len() > 1);
+        let quads: usize = geometry.iter().map(|(_, indices)| indices.len() / 6).sum();
+        assert_eq!(quads, count);
+    }
+
+    #[test]
+    fn test_line_ex() {
+        let mut gl = QuadGl::headless();
+        draw_line_ex(&mut gl, vec2(0., 5.), vec2(20., 5.), 2., 1., Color::WHITE);
+
+        let geometry = gl.queued_geometry();
+        let (vertices, _) = geometry[0];
+        assert_eq!(bounds(vertices), (vec2(0., 3.5), vec2(20., 6.5)));
+
+        // a zero length line draws nothing
+        let mut gl = QuadGl::headless();
+        draw_line_ex(&mut gl, vec2(3., 3.), vec2(3., 3.), 2., 1., Color::WHITE);
+        assert!(gl.queued_geometry().is_empty());
+    }
+}
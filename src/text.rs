@@ -5,15 +5,18 @@ use std::collections::HashMap;
 use crate::{
     Color,
     math::{vec3, Rect},
-    texture::{Image, TextureHandle, Batcher, TexturesContext},
+    texture::{Image, TextureHandle, TexturesContext},
     //Error,
 };
-use crate::quad_gl::QuadGl;
+use crate::dpi::DpiScaler;
+use crate::quad_gl::{DrawMode, QuadGl, Vertex};
 
-use glam::vec2;
+use glam::{vec2, Vec2};
 
-use std::sync::{Arc, Mutex};
+use std::cell::RefCell;
+use std::sync::{Arc, Mutex, Weak};
 pub(crate) mod atlas;
+mod sdf;
 
 use atlas::{Atlas, SpriteKey};
 
@@ -31,6 +34,8 @@ pub struct Font {
     font: Arc<fontdue::Font>,
     atlas: Arc<Mutex<Atlas>>,
     characters: Arc<Mutex<HashMap<(char, u16), CharacterInfo>>>,
+    sdf: bool,
+    strict_mode: bool,
 }
 
 fn require_fn_to_be_send() {
@@ -47,62 +52,162 @@ impl std::fmt::Debug for Font {
 }
 
 impl Font {
-    pub(crate) fn load_from_bytes(atlas: Arc<Mutex<Atlas>>, bytes: &[u8]) -> Result<Font, i32> {
+    pub(crate) fn load_from_bytes(
+        atlas: Arc<Mutex<Atlas>>,
+        bytes: &[u8],
+        settings: fontdue::FontSettings,
+    ) -> Result<Font, i32> {
+        let font = fontdue::Font::from_bytes(&bytes[..], settings).map_err(|x| -1)?;
+
+        // text is only ever laid out horizontally, fonts without horizontal
+        // metrics can't be drawn at all
+        if font.horizontal_line_metrics(1.0).is_none() {
+            return Err(-1);
+        }
+
         Ok(Font {
-            font: Arc::new(fontdue::Font::from_bytes(
-                &bytes[..],
-                fontdue::FontSettings::default(),
-            ).map_err(|x| -1)?),
+            font: Arc::new(font),
             characters: Arc::new(Mutex::new(HashMap::new())),
             atlas,
+            sdf: false,
+            strict_mode: false,
         })
     }
 
-    pub(crate) fn ascent(&self, font_size: f32) -> f32 {
-        self.font.horizontal_line_metrics(font_size).unwrap().ascent
+    // fonts without horizontal line metrics are rejected when loading
+    fn line_metrics(&self, font_size: f32) -> fontdue::LineMetrics {
+        self.font.horizontal_line_metrics(font_size).unwrap()
     }
 
-    pub(crate) fn descent(&self, font_size: f32) -> f32 {
-        self.font
-            .horizontal_line_metrics(font_size)
-            .unwrap()
-            .descent
+    /// Distance from the baseline to the top of the tallest glyphs, at the given
+    /// font size. Positive, in the same units as `font_size`.
+    pub fn ascent(&self, font_size: f32) -> f32 {
+        self.line_metrics(font_size).ascent
+    }
+
+    /// Distance from the baseline to the bottom of the lowest glyphs, at the given
+    /// font size. Usually negative, as it lies below the baseline.
+    pub fn descent(&self, font_size: f32) -> f32 {
+        self.line_metrics(font_size).descent
+    }
+
+    /// Distance between the baselines of two consecutive lines of text at the given
+    /// font size: `ascent - descent` plus the font's line gap.
+    pub fn line_height(&self, font_size: f32) -> f32 {
+        self.line_metrics(font_size).new_line_size
+    }
+
+    /// Whether the font has a glyph for `character`. Characters without one are
+    /// drawn as the font's "missing glyph", or not at all.
+    pub fn has_glyph(&self, character: char) -> bool {
+        self.font.lookup_glyph_index(character) != 0
+    }
+
+    /// Whether glyphs are cached as signed distance fields, which stay sharp when
+    /// scaled. See "FontLoadOptions::sdf".
+    pub fn is_sdf(&self) -> bool {
+        self.sdf
+    }
+
+    /// In strict mode, a warning is logged whenever a glyph has to be rasterized while
+    /// drawing or measuring text, because it wasn't pre-cached. Rasterizing glyphs can
+    /// cause a hitch the first time text is drawn, so this helps to find the characters
+    /// and sizes worth pre-caching with "populate_font_cache" or "FontLoadOptions".
+    ///
+    /// Note that glyphs are cached at their DPI-scaled size, which is the size logged.
+    /// Off by default. Only affects this copy of the font, not its clones.
+    pub fn set_strict_mode(&mut self, enabled: bool) {
+        self.strict_mode = enabled;
+    }
+
+    /// Whether strict mode is enabled. See "set_strict_mode".
+    pub fn is_strict_mode(&self) -> bool {
+        self.strict_mode
+    }
+
+    /// Size glyphs of the given size are cached at, and the factor from the cached
+    /// glyph's metrics to the requested size.
+    fn cached_size(&self, size: u16) -> (u16, f32) {
+        if self.sdf {
+            (sdf::SDF_FONT_SIZE, size as f32 / sdf::SDF_FONT_SIZE as f32)
+        } else {
+            (size, 1.)
+        }
     }
 
-    pub(crate) fn cache_glyph(&self, character: char, size: u16) {
+    /// Rasterizes a glyph into the atlas, unless it was already. Returns whether it had
+    /// to be rasterized.
+    pub(crate) fn cache_glyph(&self, character: char, size: u16) -> bool {
+        let (size, _) = self.cached_size(size);
+
         if self
             .characters
             .lock()
             .unwrap()
             .contains_key(&(character, size))
         {
-            return;
+            return false;
         }
 
+        // glyphs with a vertical advance are still laid out horizontally,
+        // their advance_height is ignored
         let (metrics, bitmap) = self.font.rasterize(character, size as f32);
 
-        if metrics.advance_height != 0.0 {
-            panic!("Vertical fonts are not supported");
+        let (mut width, mut height) = (metrics.width as u16, metrics.height as u16);
+        let (mut offset_x, mut offset_y) = (metrics.xmin, metrics.ymin);
+        let mut bitmap = bitmap;
+
+        if self.sdf {
+            let (field, field_width, field_height) =
+                sdf::signed_distance_field(&bitmap, width, height);
+            bitmap = field;
+            width = field_width;
+            height = field_height;
+            offset_x -= sdf::SPREAD as i32;
+            offset_y -= sdf::SPREAD as i32;
         }
 
-        let (width, height) = (metrics.width as u16, metrics.height as u16);
+        let mut atlas = self.atlas.lock().unwrap();
+        // distance fields are thresholded by the shader, premultiplying would break them
+        let premultiplied_alpha = atlas.premultiplied_alpha && !self.sdf;
+        let alpha_atlas = atlas.format() == miniquad::TextureFormat::Alpha;
+        let sprite = atlas.new_unique_id();
+        let image = Image {
+            bytes: bitmap
+                .iter()
+                .flat_map(|coverage| {
+                    // the shader expands coverage back out to premultiplied or straight alpha
+                    if alpha_atlas {
+                        vec![*coverage]
+                    } else if premultiplied_alpha {
+                        vec![*coverage, *coverage, *coverage, *coverage]
+                    } else {
+                        vec![255, 255, 255, *coverage]
+                    }
+                })
+                .collect(),
+            width,
+            height,
+        };
 
-        let sprite = self.atlas.lock().unwrap().new_unique_id();
-        self.atlas.lock().unwrap().cache_sprite(
-            sprite,
-            Image {
-                bytes: bitmap
-                    .iter()
-                    .flat_map(|coverage| vec![255, 255, 255, *coverage])
-                    .collect(),
-                width,
-                height,
-            },
-        );
+        if atlas.cache_sprite(sprite, image).is_err() {
+            // the glyph is still laid out, but drawn as nothing. Glyphs already
+            // queued for drawing sample the atlas, so it can't be cleared to make
+            // room until the end of the frame
+            if atlas.sprites.is_empty() {
+                log::error!(
+                    "The glyph {:?} at size {} is too large for the font atlas",
+                    character,
+                    size
+                );
+            } else if !atlas.full {
+                log::warn!("Font atlas is full, its glyph cache will be cleared");
+                atlas.full = true;
+            }
+        }
+        drop(atlas);
         let advance = metrics.advance_width;
 
-        let (offset_x, offset_y) = (metrics.xmin, metrics.ymin);
-
         let character_info = CharacterInfo {
             advance,
             offset_x,
@@ -114,9 +219,12 @@ impl Font {
             .lock()
             .unwrap()
             .insert((character, size), character_info);
+
+        true
     }
 
     pub(crate) fn get(&self, character: char, size: u16) -> Option<CharacterInfo> {
+        let (size, _) = self.cached_size(size);
         self.characters
             .lock()
             .unwrap()
@@ -141,53 +249,200 @@ impl Font {
         font_scale_x: f32,
         font_scale_y: f32,
     ) -> TextDimensions {
-        let dpi_scaling = miniquad::window::dpi_scale();
-        let font_size = (font_size as f32 * dpi_scaling).ceil() as u16;
-
-        for character in text.chars() {
-            if self
-                .characters
-                .lock()
-                .unwrap()
-                .contains_key(&(character, font_size))
-                == false
-            {
-                self.cache_glyph(character, font_size);
-            }
-        }
+        self.measure_text_with_params(
+            text,
+            font_size,
+            font_scale_x,
+            font_scale_y,
+            &TextParams::default(),
+        )
+    }
 
-        let mut width = 0.;
-        let mut min_y = f32::MAX;
-        let mut max_y = -f32::MAX;
+    /// Measures text as "draw_text_ex" would lay it out with the given params.
+    /// `font_scale` and `font_scale_aspect` in `params` are ignored in favour of
+    /// the explicit scales.
+    pub fn measure_text_with_params(
+        &self,
+        text: &str,
+        font_size: u16,
+        font_scale_x: f32,
+        font_scale_y: f32,
+        params: &TextParams,
+    ) -> TextDimensions {
+        self.measure_text_with_params_at_dpi(
+            text,
+            font_size,
+            font_scale_x,
+            font_scale_y,
+            params,
+            DpiScaler::current(),
+        )
+    }
+
+    /// Like "measure_text", but for an explicit DPI scale factor rather than
+    /// the window's. This doesn't need a window to exist, so text can be
+    /// measured during setup, or laid out for a display other than the
+    /// current one.
+    pub fn measure_text_at_dpi(
+        &self,
+        text: &str,
+        font_size: u16,
+        font_scale: f32,
+        dpi_scaling: f32,
+    ) -> TextDimensions {
+        self.measure_text_with_params_at_dpi(
+            text,
+            font_size,
+            font_scale,
+            font_scale,
+            &TextParams::default(),
+            DpiScaler::new(dpi_scaling),
+        )
+    }
+
+    fn measure_text_with_params_at_dpi(
+        &self,
+        text: &str,
+        font_size: u16,
+        font_scale_x: f32,
+        font_scale_y: f32,
+        params: &TextParams,
+        dpi: DpiScaler,
+    ) -> TextDimensions {
+        let font_size = dpi.to_physical_font_size(font_size);
+
+        self.cache_text(text, font_size);
 
         let atlas = self.atlas.lock().unwrap();
+        let characters = self.characters.lock().unwrap();
 
-        for character in text.chars() {
-            if let Some(font_data) = self.characters.lock().unwrap().get(&(character, font_size)) {
-                let glyph = atlas.get(font_data.sprite).unwrap().rect;
-                width += font_data.advance * font_scale_x;
+        let glyphs = self
+            .text_glyphs(&atlas, &characters, text, font_size, params.kerning)
+            .into_iter()
+            .map(|(metrics, _)| metrics)
+            .collect::<Vec<_>>();
+        let tab_stop = self.tab_stop(&characters, font_size, params) * font_scale_x;
 
-                if min_y > font_data.offset_y as f32 * font_scale_y {
-                    min_y = font_data.offset_y as f32 * font_scale_y;
-                }
-                if max_y < glyph.h as f32 * font_scale_y + font_data.offset_y as f32 * font_scale_y
-                {
-                    max_y =
-                        glyph.h as f32 * font_scale_y + font_data.offset_y as f32 * font_scale_y;
-                }
+        measure_glyphs(&glyphs, font_scale_x, font_scale_y, dpi.scale(), tab_stop)
+    }
+
+    /// Caches every glyph `text` needs at the given (DPI-scaled) size.
+    fn cache_text(&self, text: &str, font_size: u16) {
+        // tab stops are measured in spaces
+        let characters = std::iter::once(' ').chain(text.chars().filter(|character| *character != '\t'));
+
+        for character in characters {
+            if self.cache_glyph(character, font_size) && self.strict_mode {
+                log::warn!(
+                    "The glyph {:?} at size {} was rasterized while drawing, it could be pre-cached",
+                    character,
+                    self.cached_size(font_size).0
+                );
             }
         }
+    }
 
-        let height = max_y - min_y;
-        TextDimensions {
-            width: width / dpi_scaling,
-            height: height / dpi_scaling,
-            offset_y: max_y / dpi_scaling,
+    /// Metrics and atlas rect of each glyph of `text`, which must have been cached
+    /// with "cache_text".
+    /// With `kerning`, each glyph's advance includes the font's kerning
+    /// adjustment for the pair it forms with the following character.
+    fn text_glyphs(
+        &self,
+        atlas: &Atlas,
+        characters: &HashMap<(char, u16), CharacterInfo>,
+        text: &str,
+        font_size: u16,
+        kerning: bool,
+    ) -> Vec<(GlyphMetrics, Rect)> {
+        let mut chars = text.chars().peekable();
+        let mut glyphs = Vec::with_capacity(text.len());
+        let (cached_size, scale) = self.cached_size(font_size);
+
+        while let Some(character) = chars.next() {
+            if character == '\t' {
+                glyphs.push((GlyphMetrics::TAB, Rect::new(0., 0., 0., 0.)));
+                continue;
+            }
+
+            // the cache is emptied if the atlas fills up, possibly part way
+            // through caching this text
+            let font_data = match characters.get(&(character, cached_size)) {
+                Some(font_data) => font_data.clone(),
+                None => CharacterInfo {
+                    advance: self.font.metrics(character, cached_size as f32).advance_width,
+                    offset_x: 0,
+                    offset_y: 0,
+                    sprite: SpriteKey::Id(0),
+                },
+            };
+            let glyph = atlas
+                .get(font_data.sprite)
+                .map_or(Rect::new(0., 0., 0., 0.), |sprite| sprite.rect);
+            let kern = match chars.peek() {
+                Some(&next) if kerning && next != '\t' => self
+                    .font
+                    .horizontal_kern(character, next, font_size as f32)
+                    .unwrap_or(0.),
+                _ => 0.,
+            };
+            let metrics = GlyphMetrics {
+                offset_x: font_data.offset_x as f32 * scale,
+                offset_y: font_data.offset_y as f32 * scale,
+                advance: font_data.advance * scale + kern,
+                width: glyph.w * scale,
+                height: glyph.h * scale,
+                tab: false,
+            };
+            glyphs.push((metrics, glyph));
         }
+
+        glyphs
+    }
+
+    /// Distance between tab stops in DPI-scaled pixels, before font scaling.
+    fn tab_stop(
+        &self,
+        characters: &HashMap<(char, u16), CharacterInfo>,
+        font_size: u16,
+        params: &TextParams,
+    ) -> f32 {
+        let (cached_size, scale) = self.cached_size(font_size);
+        characters[&(' ', cached_size)].advance * scale * params.tab_width
+    }
+
+    /// Half the width of the antialiased edge of SDF glyphs drawn at the given
+    /// (DPI-scaled) size and horizontal scale, in distance field units.
+    fn sdf_smoothing(&self, font_size: u16, font_scale: f32) -> f32 {
+        let (_, scale) = self.cached_size(font_size);
+        let pixels_per_texel = (scale * font_scale).max(f32::EPSILON);
+
+        // half a screen pixel, where a texel covers 1 / (2 * SPREAD) of the field
+        0.5 / pixels_per_texel / (sdf::SPREAD as f32 * 2.)
     }
 }
 
 impl Font {
+    /// Shortens `text`, replacing its end with an ellipsis, so that it measures
+    /// no wider than `max_width` when drawn with the given params. Text which
+    /// already fits is returned unchanged.
+    ///
+    /// Uses "…" if the font has a glyph for it, and "..." otherwise.
+    pub fn ellipsize<'a>(
+        &self,
+        text: &'a str,
+        max_width: f32,
+        font_size: u16,
+        params: &TextParams,
+    ) -> std::borrow::Cow<'a, str> {
+        let ellipsis = if self.has_glyph('…') { "…" } else { "..." };
+        let font_scale_x = params.font_scale * params.font_scale_aspect;
+
+        ellipsize_with(text, ellipsis, max_width, |text| {
+            self.measure_text_with_params(text, font_size, font_scale_x, params.font_scale, params)
+                .width
+        })
+    }
+
     /// List of ascii characters, may be helpful in combination with "populate_font_cache"
     pub fn ascii_character_list() -> Vec<char> {
         (0..255).filter_map(::std::char::from_u32).collect()
@@ -223,6 +478,53 @@ impl Font {
         self.atlas.lock().unwrap().set_filter(ctx, filter_mode);
     }
 
+    /// Rasterizes glyphs with premultiplied alpha and draws them with a premultiplied
+    /// alpha blend mode. This avoids the dark fringes straight alpha produces around
+    /// text drawn over bright backgrounds.
+    ///
+    /// Disabled by default. Changing it discards all cached glyphs.
+    pub fn set_premultiplied_alpha(&mut self, enabled: bool) {
+        let mut atlas = self.atlas.lock().unwrap();
+        if atlas.premultiplied_alpha == enabled {
+            return;
+        }
+
+        atlas.premultiplied_alpha = enabled;
+        atlas.clear();
+        self.characters.lock().unwrap().clear();
+    }
+
+    pub fn premultiplied_alpha(&self) -> bool {
+        self.atlas.lock().unwrap().premultiplied_alpha
+    }
+
+    /// Whether a glyph didn't fit in the font's glyph cache, so it was drawn as
+    /// nothing. The cache is cleared, making room for the glyph again, at the end
+    /// of the frame for fonts created through the window's renderer. Other fonts
+    /// need "clear_cache" calling once nothing drawn with them is still queued.
+    pub fn is_cache_full(&self) -> bool {
+        self.atlas.lock().unwrap().full
+    }
+
+    /// Discards every cached glyph, so glyphs are rasterized again on their next use.
+    ///
+    /// Glyphs are cached per DPI-scaled font size, so after a DPI or content scale
+    /// change the glyphs cached at the old scale are never used again. Fonts created
+    /// through the window's renderer have their cache cleared automatically when that
+    /// happens.
+    pub fn clear_cache(&self) {
+        let mut atlas = self.atlas.lock().unwrap();
+        atlas.clear();
+        self.characters.lock().unwrap().clear();
+    }
+
+    pub(crate) fn downgrade_cache(&self) -> WeakFontCache {
+        WeakFontCache {
+            atlas: Arc::downgrade(&self.atlas),
+            characters: Arc::downgrade(&self.characters),
+        }
+    }
+
     // pub fn texture(&self) -> Texture2D {
     //     let font = get_context().fonts_storage.get_font(*self);
 
@@ -230,21 +532,78 @@ impl Font {
     // }
 }
 
+/// Reference to the glyph cache of a [Font] which does not keep the font alive.
+pub(crate) struct WeakFontCache {
+    atlas: Weak<Mutex<Atlas>>,
+    characters: Weak<Mutex<HashMap<(char, u16), CharacterInfo>>>,
+}
+
+impl WeakFontCache {
+    /// Clears the glyph cache, returns false if the font has already been dropped.
+    pub(crate) fn clear(&self) -> bool {
+        match (self.atlas.upgrade(), self.characters.upgrade()) {
+            (Some(atlas), Some(characters)) => {
+                atlas.lock().unwrap().clear();
+                characters.lock().unwrap().clear();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Clears the glyph cache if a glyph didn't fit in it, returns false if the
+    /// font has already been dropped.
+    pub(crate) fn clear_if_full(&self) -> bool {
+        match (self.atlas.upgrade(), self.characters.upgrade()) {
+            (Some(atlas), Some(characters)) => {
+                let mut atlas = atlas.lock().unwrap();
+                if atlas.full {
+                    atlas.clear();
+                    characters.lock().unwrap().clear();
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
 /// Arguments for "draw_text_ex" function such as font, font_size etc
 #[derive(Debug, Clone)]
 pub struct TextParams
 {
     /// The glyphs sizes actually drawn on the screen will be font_size * font_scale
-    /// However with font_scale too different from 1.0 letters may be blurry
+    /// However with font_scale too different from 1.0 letters may be blurry,
+    /// unless the font was loaded with "FontLoadOptions::sdf"
     pub font_scale: f32,
     /// Font X axis would be scaled by font_scale * font_scale_aspect
     /// and Y axis would be scaled by font_scale
     /// Default is 1.0
     pub font_scale_aspect: f32,
-    /// Text rotation in radian
+    /// Text rotation in radians, about the start of the text's baseline
     /// Default is 0.0
     pub rotation: f32,
+    /// Rotate the text about this point rather than the point it is drawn at,
+    /// for example the center of the text's bounds. The text turns as one block.
+    /// Like the point the text is drawn at, this is in screen coordinates.
+    /// Default is None
+    pub rotation_pivot: Option<Vec2>,
+    /// Distance between tab stops, in widths of the font's space character.
+    /// A tab moves the following text to the next tab stop.
+    /// Default is 4.0
+    pub tab_width: f32,
+    /// Adjust the spacing of character pairs such as "AV" using the font's
+    /// kerning table.
+    /// Default is true
+    pub kerning: bool,
     pub color: Color,
+    /// Which line of the text is placed at the "y" coordinate it is drawn at.
+    /// Default is TextBaseline::Alphabetic
+    pub baseline: TextBaseline,
+    /// Fills the text's measured bounds with this color before drawing the text,
+    /// as for highlighting it.
+    /// Default is None
+    pub background: Option<Color>,
 }
 
 impl Default for TextParams {
@@ -254,11 +613,90 @@ impl Default for TextParams {
             font_scale_aspect: 1.0,
             color: Color::BLACK,
             rotation: 0.0,
+            rotation_pivot: None,
+            tab_width: 4.0,
+            kerning: true,
+            background: None,
+            baseline: TextBaseline::Alphabetic,
+        }
+    }
+}
+
+/// The line through a run of text which is positioned at the point it is drawn at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextBaseline {
+    /// The line most letters sit on, with only descenders such as in "g" and "y"
+    /// below it. Text drawn at the same "y" shares this line, whatever its size.
+    Alphabetic,
+    /// The font's ascent, above the tallest glyphs.
+    Top,
+    /// Half way between the font's ascent and descent.
+    Middle,
+    /// The font's descent, below the lowest glyphs.
+    Bottom,
+}
+
+/// Controls how a font is loaded: which face of a collection to use, and which glyphs are
+/// rasterized up front. Glyphs that aren't pre-cached are rasterized the first time they're
+/// drawn.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FontLoadOptions {
+    /// Characters to pre-cache
+    /// Default is "Font::ascii_character_list"
+    pub characters: Vec<char>,
+    /// Pixel sizes to pre-cache each character at, after DPI scaling.
+    /// Ignored for SDF fonts, which cache each glyph at a single size.
+    /// Default is [15]
+    pub sizes: Vec<u16>,
+    /// Cache glyphs as signed distance fields and draw them with a thresholding
+    /// shader, so text stays sharp at any size and "font_scale" instead of
+    /// blurring away from the size it was rasterized at. Premultiplied alpha
+    /// is not supported for SDF fonts.
+    /// Default is false
+    pub sdf: bool,
+    /// The index of the face to load from a font collection (`.ttc`), which bundles several
+    /// faces in one file. Ignored for single fonts.
+    /// Default is 0
+    pub collection_index: u32,
+    /// The size in pixels per em that the font's outlines are prepared for. Glyphs look best
+    /// near this size, and slightly worse far above it.
+    /// Default is 40.0
+    pub scale: f32,
+    /// Store glyphs in a single channel atlas holding only their coverage, a quarter of the
+    /// memory of the usual RGBA atlas. Worthwhile when pre-caching large character ranges.
+    /// Not supported on Metal, where the RGBA atlas is used instead.
+    /// Default is false
+    pub alpha_atlas: bool,
+}
+
+impl FontLoadOptions {
+    /// Options which skip pre-caching entirely.
+    pub fn no_precache() -> FontLoadOptions {
+        FontLoadOptions {
+            characters: vec![],
+            sizes: vec![],
+            ..Default::default()
+        }
+    }
+}
+
+impl Default for FontLoadOptions {
+    fn default() -> FontLoadOptions {
+        FontLoadOptions {
+            characters: Font::ascii_character_list(),
+            sizes: vec![15],
+            sdf: false,
+            collection_index: 0,
+            scale: 40.0,
+            alpha_atlas: false,
         }
     }
 }
 
 /// Load font from bytes array, may be use in combination with include_bytes!
+///
+/// Fails if the font can't be parsed, or if it is a vertical-only font without
+/// horizontal metrics.
 /// ```ignore
 /// let font = load_ttf_font_from_bytes(include_bytes!("font.ttf"));
 /// ```
@@ -267,24 +705,191 @@ pub fn load_ttf_font_from_bytes(
     bytes: &[u8]
     ) -> Result<Font, i32> 
 {
-    let atlas = Arc::new(Mutex::new(Atlas::new(
+    load_ttf_font_from_bytes_with_options(quad_context, bytes, &FontLoadOptions::default())
+}
+
+/// Load font from bytes array, pre-caching the glyphs selected by `options`.
+///
+/// Fails under the same conditions as "load_ttf_font_from_bytes", or if the bytes are a font
+/// collection without a face at `options.collection_index`.
+pub fn load_ttf_font_from_bytes_with_options(
+    quad_context: &mut dyn miniquad::RenderingBackend,
+    bytes: &[u8],
+    options: &FontLoadOptions,
+) -> Result<Font, i32> {
+    let format = if !options.alpha_atlas {
+        miniquad::TextureFormat::RGBA8
+    } else if quad_context.info().backend == miniquad::Backend::Metal {
+        log::warn!("Alpha font atlases aren't supported on Metal, using an RGBA atlas");
+        miniquad::TextureFormat::RGBA8
+    } else {
+        miniquad::TextureFormat::Alpha
+    };
+    let atlas = Arc::new(Mutex::new(Atlas::with_format(
         quad_context,
         miniquad::FilterMode::Linear,
+        format,
     )));
 
-    let mut font = Font::load_from_bytes(atlas.clone(), bytes)?;
+    let settings = fontdue::FontSettings {
+        collection_index: options.collection_index,
+        scale: options.scale,
+    };
+    let mut font = Font::load_from_bytes(atlas.clone(), bytes, settings)?;
+    font.sdf = options.sdf;
 
-    font.populate_font_cache(&Font::ascii_character_list(), 15);
+    for size in &options.sizes {
+        font.populate_font_cache(&options.characters, *size);
+    }
 
     Ok(font)
 }
 
+/// Size and placement of a rasterized glyph, in DPI-scaled pixels.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct GlyphMetrics {
+    pub offset_x: f32,
+    pub offset_y: f32,
+    pub advance: f32,
+    pub width: f32,
+    pub height: f32,
+    /// A tab, which moves the pen to the next tab stop and isn't drawn
+    pub tab: bool,
+}
+
+impl GlyphMetrics {
+    pub const TAB: GlyphMetrics = GlyphMetrics {
+        offset_x: 0.,
+        offset_y: 0.,
+        advance: 0.,
+        width: 0.,
+        height: 0.,
+        tab: true,
+    };
+}
+
+/// Pen position after `glyph`, which was placed with the pen at `pen`.
+/// `tab_stop` is the distance between tab stops, in the same units as `pen`.
+fn advance_pen(pen: f32, glyph: &GlyphMetrics, font_scale_x: f32, tab_stop: f32) -> f32 {
+    if glyph.tab && tab_stop > 0. {
+        ((pen / tab_stop).floor() + 1.) * tab_stop
+    } else {
+        pen + glyph.advance * font_scale_x
+    }
+}
+
+/// Computes the screen-space corners of each glyph quad (clockwise, starting at
+/// the top left) for a run of text whose baseline starts at `origin`.
+/// The run is rotated by `rotation` radians as a rigid block about `origin`.
+/// `tab_stop` is the distance between tab stops in DPI-scaled pixels, with
+/// `font_scale_x` applied.
+pub(crate) fn layout_glyph_quads(
+    glyphs: &[GlyphMetrics],
+    origin: Vec2,
+    font_scale_x: f32,
+    font_scale_y: f32,
+    dpi_scaling: f32,
+    rotation: f32,
+    tab_stop: f32,
+) -> Vec<[Vec2; 4]> {
+    let (sin, cos) = rotation.sin_cos();
+    let rotate = |p: Vec2| origin + vec2(p.x * cos - p.y * sin, p.x * sin + p.y * cos);
+
+    let mut total_width = 0.;
+    glyphs
+        .iter()
+        .map(|glyph| {
+            let left = (glyph.offset_x * font_scale_x + total_width) / dpi_scaling;
+            let top = -(glyph.height + glyph.offset_y) * font_scale_y / dpi_scaling;
+            let w = glyph.width * font_scale_x / dpi_scaling;
+            let h = glyph.height * font_scale_y / dpi_scaling;
+
+            total_width = advance_pen(total_width, glyph, font_scale_x, tab_stop);
+
+            [
+                rotate(vec2(left, top)),
+                rotate(vec2(left + w, top)),
+                rotate(vec2(left + w, top + h)),
+                rotate(vec2(left, top + h)),
+            ]
+        })
+        .collect()
+}
+
+/// Measures a run of glyphs laid out by [layout_glyph_quads].
+pub(crate) fn measure_glyphs(
+    glyphs: &[GlyphMetrics],
+    font_scale_x: f32,
+    font_scale_y: f32,
+    dpi_scaling: f32,
+    tab_stop: f32,
+) -> TextDimensions {
+    if glyphs.is_empty() {
+        return TextDimensions {
+            width: 0.,
+            height: 0.,
+            offset_y: 0.,
+        };
+    }
+
+    let mut width = 0.;
+    let mut min_y = f32::MAX;
+    let mut max_y = -f32::MAX;
+
+    for glyph in glyphs {
+        width = advance_pen(width, glyph, font_scale_x, tab_stop);
+        if glyph.tab {
+            continue;
+        }
+        min_y = min_y.min(glyph.offset_y * font_scale_y);
+        max_y = max_y.max((glyph.height + glyph.offset_y) * font_scale_y);
+    }
+
+    TextDimensions {
+        width: width / dpi_scaling,
+        height: (max_y - min_y) / dpi_scaling,
+        offset_y: max_y / dpi_scaling,
+    }
+}
+
+/// Finds the longest prefix of `text` which, followed by `ellipsis`, measures
+/// no wider than `max_width`. Returns `text` itself if it already fits, or an
+/// empty string if not even the ellipsis does.
+fn ellipsize_with<'a>(
+    text: &'a str,
+    ellipsis: &str,
+    max_width: f32,
+    mut measure: impl FnMut(&str) -> f32,
+) -> std::borrow::Cow<'a, str> {
+    if measure(text) <= max_width {
+        return text.into();
+    }
+
+    let boundaries = text.char_indices().map(|(i, _)| i).collect::<Vec<_>>();
+    let truncated = |count: usize| format!("{}{}", &text[..boundaries[count]], ellipsis);
+
+    // binary search for the most characters that fit, text gets wider as it gets longer
+    let (mut fits, mut too_wide) = (0, boundaries.len());
+    if measure(&truncated(0)) > max_width {
+        return "".into();
+    }
+    while too_wide - fits > 1 {
+        let middle = (fits + too_wide) / 2;
+        if measure(&truncated(middle)) <= max_width {
+            fits = middle;
+        } else {
+            too_wide = middle;
+        }
+    }
+
+    truncated(fits).into()
+}
+
 /// Draw text with custom params such as font, font size and font scale.
 pub fn draw_text_ex(
     gl: &mut QuadGl, 
     quad_context: &mut dyn miniquad::RenderingBackend,
     textures: &TexturesContext, 
-    texture_batcher: &mut Batcher,
     text: &str, 
     x: f32, 
     y: f32, 
@@ -293,90 +898,323 @@ pub fn draw_text_ex(
     params: TextParams
     )
 {
+    draw_text_batch(gl, quad_context, textures, &[(text, x, y)], font, font_size, params);
+}
+
+/// Draw several strings sharing the same font, font size and params.
+/// Each item is the text and the `x`, `y` it is drawn at, as for "draw_text_ex".
+///
+/// All glyphs are cached and the font atlas is locked only once, and every glyph
+/// quad is emitted before any draw state changes, so the whole batch can end up
+/// in a single draw call.
+pub(crate) fn draw_text_batch(
+    gl: &mut QuadGl,
+    quad_context: &mut dyn miniquad::RenderingBackend,
+    textures: &TexturesContext,
+    items: &[(&str, f32, f32)],
+    font: &Font,
+    font_size: u16,
+    params: TextParams,
+) {
+    let laid_out = lay_out_text(font, items, font_size, &params, DpiScaler::current());
+    draw_laid_out_text(gl, quad_context, textures, font, &laid_out, vec2(0., 0.));
+}
+
+/// Glyph quads for a run of text, relative to the point its baseline starts at,
+/// with the atlas pixels each one is drawn from.
+#[derive(Debug, Clone)]
+struct LaidOutRun {
+    origin: Vec2,
+    background: [Vec2; 4],
+    glyphs: Vec<([Vec2; 4], Rect)>,
+}
+
+/// Everything needed to draw a batch of text, without looking up any glyphs.
+#[derive(Debug, Clone)]
+struct LaidOutText {
+    runs: Vec<LaidOutRun>,
+    color: Color,
+    background: Option<Color>,
+    premultiplied_alpha: bool,
+    sdf_smoothing: Option<f32>,
+    dpi_scaling: f32,
+    atlas_generation: u64,
+}
+
+fn lay_out_text(
+    font: &Font,
+    items: &[(&str, f32, f32)],
+    font_size: u16,
+    params: &TextParams,
+    dpi: DpiScaler,
+) -> LaidOutText {
     let font_scale_x = params.font_scale * params.font_scale_aspect;
     let font_scale_y = params.font_scale;
-    let dpi_scaling = miniquad::window::dpi_scale();
+    let dpi_scaling = dpi.scale();
 
-    let font_size = (font_size as f32 * dpi_scaling).ceil() as u16;
-
-    let mut total_width = 0.;
-    for character in text.chars() {
-        if !font
-            .characters
-            .lock()
-            .unwrap()
-            .contains_key(&(character, font_size))
-        {
-            font.cache_glyph(character, font_size);
+    // distance from the baseline down to the point each item is drawn at
+    let baseline_offset = {
+        let size = font_size as f32 * font_scale_y;
+        match params.baseline {
+            TextBaseline::Alphabetic => 0.,
+            TextBaseline::Top => font.ascent(size),
+            TextBaseline::Middle => (font.ascent(size) + font.descent(size)) / 2.,
+            TextBaseline::Bottom => font.descent(size),
         }
-        let mut atlas = font.atlas.lock().unwrap();
-        let font_data = &font.characters.lock().unwrap()[&(character, font_size)];
-        let glyph = atlas.get(font_data.sprite).unwrap().rect;
-        let angle_rad = params.rotation;
-        let left_coord = (font_data.offset_x as f32 * font_scale_x + total_width) * angle_rad.cos()
-            + (glyph.h as f32 * font_scale_y + font_data.offset_y as f32 * font_scale_y)
-                * angle_rad.sin();
-        let top_coord = (font_data.offset_x as f32 * font_scale_x + total_width) * angle_rad.sin()
-            + (0.0 - glyph.h as f32 * font_scale_y - font_data.offset_y as f32 * font_scale_y)
-                * angle_rad.cos();
-
-        total_width += font_data.advance * font_scale_x;
-
-        let dest = Rect::new(
-            left_coord / dpi_scaling as f32 + x,
-            top_coord / dpi_scaling as f32 + y,
-            glyph.w as f32 / dpi_scaling as f32 * font_scale_x,
-            glyph.h as f32 / dpi_scaling as f32 * font_scale_y,
-        );
-
-        let source = Rect::new(
-            glyph.x as f32,
-            glyph.y as f32,
-            glyph.w as f32,
-            glyph.h as f32,
-        );
-
-        let t = 
-            crate::texture::Texture2D {
-                texture: TextureHandle::Unmanaged(atlas.texture(quad_context)),
+    };
+    let (sin, cos) = params.rotation.sin_cos();
+    let rotate = |p: Vec2| vec2(p.x * cos - p.y * sin, p.x * sin + p.y * cos);
+    let baseline_shift = vec2(-baseline_offset * sin, baseline_offset * cos);
+
+    let font_size = dpi.to_physical_font_size(font_size);
+
+    let premultiplied_alpha = font.premultiplied_alpha() && !font.is_sdf();
+    let color = if premultiplied_alpha {
+        params.color.premultiplied()
+    } else {
+        params.color
+    };
+
+    for (text, _, _) in items {
+        font.cache_text(text, font_size);
+    }
+
+    let atlas = font.atlas.lock().unwrap();
+    let characters = font.characters.lock().unwrap();
+    let tab_stop = font.tab_stop(&characters, font_size, params) * font_scale_x;
+
+    let runs = items
+        .iter()
+        .map(|(text, x, y)| {
+            let (metrics, sources): (Vec<_>, Vec<_>) = font
+                .text_glyphs(&atlas, &characters, text, font_size, params.kerning)
+                .into_iter()
+                .unzip();
+
+            let dimensions = measure_glyphs(&metrics, font_scale_x, font_scale_y, dpi_scaling, tab_stop);
+            let (top, bottom) = (-dimensions.offset_y, dimensions.height - dimensions.offset_y);
+            let background = [
+                vec2(0., top),
+                vec2(dimensions.width, top),
+                vec2(dimensions.width, bottom),
+                vec2(0., bottom),
+            ]
+            .map(rotate);
+
+            let quads = layout_glyph_quads(
+                &metrics,
+                vec2(0., 0.),
+                font_scale_x,
+                font_scale_y,
+                dpi_scaling,
+                params.rotation,
+                tab_stop,
+            );
+            let glyphs = quads
+                .into_iter()
+                .zip(sources)
+                .zip(&metrics)
+                .filter(|(_, glyph)| !glyph.tab)
+                .map(|(glyph, _)| glyph)
+                .collect();
+
+            let origin = vec2(*x, *y);
+            let origin = match params.rotation_pivot {
+                Some(pivot) => pivot + rotate(origin - pivot),
+                None => origin,
             };
-        crate::texture::draw_texture_ex(
-            gl,
-            quad_context,
-            textures,
-            texture_batcher,
-            &t,
-            dest.x,
-            dest.y,
-            params.color,
-            crate::texture::DrawTextureParams {
-                dest_size: Some(vec2(dest.w, dest.h)),
-                source: Some(source),
-                rotation: angle_rad,
-                pivot: Option::Some(vec2(dest.x, dest.y)),
-                ..Default::default()
-            },
-        );
+
+            LaidOutRun {
+                origin: origin + baseline_shift,
+                background,
+                glyphs,
+            }
+        })
+        .collect();
+
+    LaidOutText {
+        runs,
+        color,
+        background: params.background,
+        premultiplied_alpha,
+        sdf_smoothing: font
+            .is_sdf()
+            .then(|| font.sdf_smoothing(font_size, font_scale_x.min(font_scale_y))),
+        dpi_scaling,
+        atlas_generation: atlas.generation(),
     }
 }
 
-/*
-/// Get the text center.
+fn draw_laid_out_text(
+    gl: &mut QuadGl,
+    quad_context: &mut dyn miniquad::RenderingBackend,
+    textures: &TexturesContext,
+    font: &Font,
+    laid_out: &LaidOutText,
+    offset: Vec2,
+) {
+    let dpi = DpiScaler::new(laid_out.dpi_scaling);
+    // glyph bitmaps are whole physical pixels, so keeping the origin on the
+    // physical pixel grid keeps them from being sampled between pixels
+    let origins = laid_out
+        .runs
+        .iter()
+        .map(|run| dpi.snap_to_physical(run.origin + offset))
+        .collect::<Vec<_>>();
+
+    // every background goes underneath all of the text, and is drawn before the
+    // text's draw state is set up
+    if let Some(background) = laid_out.background {
+        gl.texture(textures, None);
+        gl.draw_mode(DrawMode::Triangles);
+        for (run, origin) in laid_out.runs.iter().zip(&origins) {
+            let vertices = run
+                .background
+                .map(|p| Vertex::new(origin.x + p.x, origin.y + p.y, 0., 0., 0., background));
+            gl.geometry(&vertices, &[0, 1, 2, 0, 2, 3]);
+        }
+    }
+
+    let mut atlas = font.atlas.lock().unwrap();
+    let texture = crate::texture::Texture2D {
+        texture: TextureHandle::Unmanaged(atlas.texture(quad_context)),
+    };
+    let (atlas_width, atlas_height) = (atlas.width() as f32, atlas.height() as f32);
+    let color = laid_out.color;
+
+    let previous_premultiplied_alpha = gl.is_premultiplied_alpha();
+    let previous_pipeline = gl.get_pipeline();
+    gl.premultiplied_alpha(laid_out.premultiplied_alpha);
+    if let Some(smoothing) = laid_out.sdf_smoothing {
+        let pipeline = gl.sdf_text_pipeline();
+        gl.pipeline(Some(pipeline));
+        gl.set_uniform(pipeline, "Smoothing", smoothing);
+    }
+    gl.texture(textures, Some(&texture));
+    gl.draw_mode(DrawMode::Triangles);
+
+    for (run, origin) in laid_out.runs.iter().zip(origins) {
+        for (quad, source) in &run.glyphs {
+            let p = quad.map(|corner| origin + corner);
+            let (u0, v0) = (source.x / atlas_width, source.y / atlas_height);
+            let (u1, v1) = ((source.x + source.w) / atlas_width, (source.y + source.h) / atlas_height);
+
+            #[rustfmt::skip]
+            let vertices = [
+                Vertex::new(p[0].x, p[0].y, 0., u0, v0, color),
+                Vertex::new(p[1].x, p[1].y, 0., u1, v0, color),
+                Vertex::new(p[2].x, p[2].y, 0., u1, v1, color),
+                Vertex::new(p[3].x, p[3].y, 0., u0, v1, color),
+            ];
+            gl.geometry(&vertices, &[0, 1, 2, 0, 2, 3]);
+        }
+    }
+
+    gl.premultiplied_alpha(previous_premultiplied_alpha);
+    gl.pipeline(previous_pipeline);
+}
+
+/// Text which has been laid out ahead of time by "layout_text", so that it can be
+/// drawn repeatedly without looking up, measuring and positioning its glyphs
+/// again. Useful for text which rarely changes, such as labels and titles.
+///
+/// The layout is redone, once, when drawn if the font's glyph cache has changed since,
+/// for example because the DPI scale has changed.
+#[derive(Clone)]
+pub struct TextLayout {
+    font: Font,
+    text: String,
+    font_size: u16,
+    params: TextParams,
+    // redone in place when drawn after the glyph cache changes
+    laid_out: RefCell<LaidOutText>,
+}
+
+impl TextLayout {
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn font_size(&self) -> u16 {
+        self.font_size
+    }
+
+    pub fn params(&self) -> &TextParams {
+        &self.params
+    }
+}
+
+/// Lay out text as "draw_text_ex" would draw it at `[0, 0]`, for drawing later with
+/// "draw_text_layout". A "TextParams::rotation_pivot" is relative to the point the
+/// layout is later drawn at.
+pub fn layout_text(text: &str, font: &Font, font_size: u16, params: TextParams) -> TextLayout {
+    let laid_out = lay_out_text(font, &[(text, 0., 0.)], font_size, &params, DpiScaler::current());
+
+    TextLayout {
+        font: font.clone(),
+        text: text.to_owned(),
+        font_size,
+        params,
+        laid_out: RefCell::new(laid_out),
+    }
+}
+
+/// Draw text laid out by "layout_text", as "draw_text_ex" would draw it at `position`.
+pub(crate) fn draw_text_layout(
+    gl: &mut QuadGl,
+    quad_context: &mut dyn miniquad::RenderingBackend,
+    textures: &TexturesContext,
+    layout: &TextLayout,
+    position: Vec2,
+) {
+    let dpi = DpiScaler::current();
+    let generation = layout.font.atlas.lock().unwrap().generation();
+
+    let laid_out = refreshed_layout(&layout.laid_out, dpi.scale(), generation, || {
+        lay_out_text(
+            &layout.font,
+            &[(&layout.text, 0., 0.)],
+            layout.font_size,
+            &layout.params,
+            dpi,
+        )
+    });
+    draw_laid_out_text(gl, quad_context, textures, &layout.font, &laid_out, position);
+}
+
+/// Get `laid_out`, first replacing it with the result of `lay_out` if it was laid
+/// out at a different DPI scale or against an older atlas generation.
+fn refreshed_layout<F: FnOnce() -> LaidOutText>(
+    laid_out: &RefCell<LaidOutText>,
+    dpi_scaling: f32,
+    atlas_generation: u64,
+    lay_out: F,
+) -> std::cell::Ref<'_, LaidOutText> {
+    let stale = {
+        let laid_out = laid_out.borrow();
+        laid_out.dpi_scaling != dpi_scaling || laid_out.atlas_generation != atlas_generation
+    };
+
+    if stale {
+        *laid_out.borrow_mut() = lay_out();
+    }
+
+    laid_out.borrow()
+}
+
+/// Get the offset from the point text is drawn at to the center of the drawn text,
+/// taking the text's rotation about that point into account.
+///
+/// Drawing text at `[x, y]` centers it on `[x, y] + get_text_center(..)`.
 pub fn get_text_center(
     text: &str,
     font: &Font,
     font_size: u16,
     font_scale: f32,
     rotation: f32,
-) -> crate::Vec2 {
-    let measure = measure_text(text, font, font_size, font_scale);
-
-    let x_center = measure.width / 2.0 * rotation.cos() + measure.height / 2.0 * rotation.sin();
-    let y_center = measure.width / 2.0 * rotation.sin() - measure.height / 2.0 * rotation.cos();
-
-    crate::Vec2::new(x_center, y_center)
+) -> Vec2 {
+    font.measure_text(text, font_size, font_scale).center(rotation)
 }
-*/
 
 /// World space dimensions of the text, measured by "measure_text" function
 #[derive(Debug, Clone, Copy)]
@@ -391,6 +1229,21 @@ pub struct TextDimensions {
     pub offset_y: f32,
 }
 
+impl TextDimensions {
+    /// Offset from the point the text is drawn at to the center of the rectangle
+    /// `Rect::new(X, Y - offset_y, width, height)` the text is drawn in, with the
+    /// text rotated by `rotation` radians about the point it is drawn at.
+    pub fn center(&self, rotation: f32) -> Vec2 {
+        let (sin, cos) = rotation.sin_cos();
+        let center = vec2(self.width / 2.0, self.height / 2.0 - self.offset_y);
+
+        vec2(
+            center.x * cos - center.y * sin,
+            center.x * sin + center.y * cos,
+        )
+    }
+}
+
 /*
 pub fn measure_text(
     text: &str,
@@ -412,7 +1265,11 @@ impl FontsStorage {
     pub(crate) fn new(ctx: &mut dyn miniquad::RenderingBackend) -> FontsStorage {
         let atlas = Arc::new(Mutex::new(Atlas::new(ctx, miniquad::FilterMode::Linear)));
 
-        let default_font = Font::load_from_bytes(atlas, include_bytes!("ProggyClean.ttf")).unwrap();
+        let default_font = Font::load_from_bytes(
+            atlas,
+            include_bytes!("ProggyClean.ttf"),
+            fontdue::FontSettings::default(),
+        ).unwrap();
         FontsStorage { default_font }
     }
 }
@@ -435,3 +1292,147 @@ pub fn camera_font_scale(mat: glam::Mat4, world_font_size: f32) -> (u16, f32, f3
 
     (font_size, cam_h / scr_h, scr_h / scr_w * cam_w / cam_h)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bounding_box(quads: &[[Vec2; 4]]) -> (Vec2, Vec2) {
+        let points = quads.iter().flatten();
+        let min = points.clone().fold(Vec2::splat(f32::MAX), |a, b| a.min(*b));
+        let max = points.fold(Vec2::splat(f32::MIN), |a, b| a.max(*b));
+        (min, max)
+    }
+
+    #[test]
+    fn test_rotated_text_is_rigid() {
+        // three identical glyphs standing in for "ABC"
+        let glyph = GlyphMetrics {
+            offset_x: 1.,
+            offset_y: 0.,
+            advance: 12.,
+            width: 10.,
+            height: 12.,
+            tab: false,
+        };
+        let glyphs = [glyph; 3];
+        let origin = vec2(100., 100.);
+
+        let upright = layout_glyph_quads(&glyphs, origin, 1., 1., 1., 0., 0.);
+        let (min, max) = bounding_box(&upright);
+        assert!(min.abs_diff_eq(vec2(101., 88.), 1e-4));
+        assert!(max.abs_diff_eq(vec2(135., 100.), 1e-4));
+
+        // at 90 degrees the run reads downwards, with the glyph tops facing +x
+        let rotated =
+            layout_glyph_quads(&glyphs, origin, 1., 1., 1., std::f32::consts::FRAC_PI_2, 0.);
+        let (min, max) = bounding_box(&rotated);
+        assert!(min.abs_diff_eq(vec2(100., 101.), 1e-4));
+        assert!(max.abs_diff_eq(vec2(112., 135.), 1e-4));
+
+        // every corner keeps its distance to the pivot
+        for (a, b) in upright.iter().flatten().zip(rotated.iter().flatten()) {
+            assert!((a.distance(origin) - b.distance(origin)).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn test_text_center() {
+        // glyphs sitting above and dipping below the baseline, filling their advance
+        let glyphs = [
+            GlyphMetrics { offset_x: 0., offset_y: 0., advance: 10., width: 10., height: 14., tab: false },
+            GlyphMetrics { offset_x: 0., offset_y: -4., advance: 10., width: 10., height: 12., tab: false },
+        ];
+        let origin = vec2(50., 50.);
+
+        for dpi_scaling in [1., 2.] {
+            let dimensions = measure_glyphs(&glyphs, 1., 1., dpi_scaling, 0.);
+
+            // right angles keep the drawn bounds axis aligned, so their center is the text's
+            for rotation in [0., std::f32::consts::FRAC_PI_2, std::f32::consts::PI] {
+                let quads = layout_glyph_quads(&glyphs, origin, 1., 1., dpi_scaling, rotation, 0.);
+                let (min, max) = bounding_box(&quads);
+                let drawn = (min + max) / 2.;
+
+                assert!(
+                    (origin + dimensions.center(rotation)).abs_diff_eq(drawn, 1e-4),
+                    "rotation {}, dpi {}",
+                    rotation,
+                    dpi_scaling
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_ellipsize() {
+        // every character, including the ellipsis, is 10 wide
+        let measure = |text: &str| text.chars().count() as f32 * 10.;
+
+        assert_eq!(ellipsize_with("short", "…", 50., measure), "short");
+        assert_eq!(ellipsize_with("longer text", "…", 50., measure), "long…");
+        assert_eq!(ellipsize_with("longer text", "...", 50., measure), "lo...");
+        assert_eq!(ellipsize_with("héllo wörld", "…", 45., measure), "hél…");
+        assert_eq!(ellipsize_with("longer text", "…", 15., measure), "…");
+        assert_eq!(ellipsize_with("longer text", "…", 5., measure), "");
+    }
+
+    #[test]
+    fn test_tab_stops() {
+        let glyph = GlyphMetrics {
+            offset_x: 0.,
+            offset_y: 0.,
+            advance: 10.,
+            width: 10.,
+            height: 10.,
+            tab: false,
+        };
+        let origin = vec2(0., 0.);
+
+        // "a\tb" and "aaaaa\tb" with tab stops every 40 pixels
+        let short = [glyph, GlyphMetrics::TAB, glyph];
+        let quads = layout_glyph_quads(&short, origin, 1., 1., 1., 0., 40.);
+        assert_eq!(quads[2][0].x, 40.);
+        assert_eq!(measure_glyphs(&short, 1., 1., 1., 40.).width, 50.);
+
+        let long = [glyph, glyph, glyph, glyph, glyph, GlyphMetrics::TAB, glyph];
+        let quads = layout_glyph_quads(&long, origin, 1., 1., 1., 0., 40.);
+        assert_eq!(quads[6][0].x, 80.);
+
+        // a tab right on a tab stop still moves to the next one
+        let aligned = [glyph, glyph, glyph, glyph, GlyphMetrics::TAB, glyph];
+        let quads = layout_glyph_quads(&aligned, origin, 1., 1., 1., 0., 40.);
+        assert_eq!(quads[5][0].x, 80.);
+
+        // scaled text scales its tab stops too
+        let quads = layout_glyph_quads(&short, origin, 2., 2., 1., 0., 80.);
+        assert_eq!(quads[2][0].x, 80.);
+    }
+
+    #[test]
+    fn test_layout_refreshed_once() {
+        let laid_out = |atlas_generation| LaidOutText {
+            runs: vec![],
+            color: Color::WHITE,
+            background: None,
+            premultiplied_alpha: false,
+            sdf_smoothing: None,
+            dpi_scaling: 1.,
+            atlas_generation,
+        };
+        let layout = RefCell::new(laid_out(0));
+        let mut layouts = 0;
+
+        assert_eq!(refreshed_layout(&layout, 1., 0, || unreachable!()).atlas_generation, 0);
+
+        // the first draw after the atlas changes lays out again and keeps the result
+        for _ in 0..2 {
+            let refreshed = refreshed_layout(&layout, 1., 1, || {
+                layouts += 1;
+                laid_out(1)
+            });
+            assert_eq!(refreshed.atlas_generation, 1);
+        }
+        assert_eq!(layouts, 1);
+    }
+}
@@ -1,7 +1,6 @@
 use crate::{
     math::Rect,
     texture::{Image, Texture2D},
-    Color,
 };
 
 use std::collections::HashMap;
@@ -16,6 +15,10 @@ pub enum SpriteKey {
     Texture(miniquad::TextureId),
     Id(u64),
 }
+/// Returned when a sprite doesn't fit in an [Atlas], even at its maximum size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AtlasFull;
+
 pub struct Atlas {
     texture: miniquad::TextureId,
     image: Image,
@@ -26,9 +29,24 @@ pub struct Atlas {
 
     pub dirty: bool,
 
+    /// Whether sprites are stored with their color premultiplied by alpha.
+    /// Only used to remember the format sprites were cached in.
+    pub premultiplied_alpha: bool,
+
+    /// Set when a sprite didn't fit, until the atlas is next cleared. Used to
+    /// put off clearing the atlas until nothing queued for drawing uses it.
+    pub full: bool,
+
     filter: miniquad::FilterMode,
 
+    /// Either `RGBA8`, or `Alpha` for atlases holding only coverage, such as
+    /// glyphs. Sprites are cached in the same format as the atlas.
+    format: miniquad::TextureFormat,
+
     unique_id: u64,
+
+    /// Incremented whenever existing sprites move within the atlas.
+    generation: u64,
 }
 
 impl Drop for Atlas {
@@ -43,10 +61,31 @@ impl Atlas {
     const GAP: u16 = 2;
     // well..
     const UNIQUENESS_OFFSET: u64 = 100000;
+    // size of a freshly created or repacked atlas
+    const INITIAL_SIZE: u16 = 512;
+    /// The atlas never grows beyond this size in either dimension.
+    pub const MAX_SIZE: u16 = 8192;
 
     pub fn new(ctx: &mut dyn miniquad::RenderingBackend, filter: miniquad::FilterMode) -> Atlas {
-        let image = Image::gen_image_color(512, 512, Color::from_rgba(0.0, 0.0, 0.0, 0.0));
-        let texture = ctx.new_texture_from_rgba8(image.width, image.height, &image.bytes);
+        Self::with_format(ctx, filter, miniquad::TextureFormat::RGBA8)
+    }
+
+    /// Creates an atlas storing sprites as `format`, which must be `RGBA8` or
+    /// `Alpha`. An `Alpha` atlas takes a quarter of the memory, and is drawn as
+    /// white with the stored coverage as its alpha.
+    pub fn with_format(
+        ctx: &mut dyn miniquad::RenderingBackend,
+        filter: miniquad::FilterMode,
+        format: miniquad::TextureFormat,
+    ) -> Atlas {
+        assert!(
+            format == miniquad::TextureFormat::RGBA8 || format == miniquad::TextureFormat::Alpha,
+            "Unsupported atlas format {:?}",
+            format
+        );
+
+        let image = Self::blank_image(format, Self::INITIAL_SIZE, Self::INITIAL_SIZE);
+        let texture = Self::new_texture(ctx, format, &image);
         ctx.texture_set_filter(
             texture,
             miniquad::FilterMode::Nearest,
@@ -59,13 +98,52 @@ impl Atlas {
             cursor_x: 0,
             cursor_y: 0,
             dirty: false,
+            premultiplied_alpha: false,
+            full: false,
             max_line_height: 0,
             sprites: HashMap::new(),
             filter,
+            format,
             unique_id: Self::UNIQUENESS_OFFSET,
+            generation: 0,
+        }
+    }
+
+    fn bytes_per_pixel(format: miniquad::TextureFormat) -> usize {
+        match format {
+            miniquad::TextureFormat::Alpha => 1,
+            _ => 4,
+        }
+    }
+
+    fn blank_image(format: miniquad::TextureFormat, width: u16, height: u16) -> Image {
+        Image {
+            bytes: vec![0; width as usize * height as usize * Self::bytes_per_pixel(format)],
+            width,
+            height,
         }
     }
 
+    fn new_texture(
+        ctx: &mut dyn miniquad::RenderingBackend,
+        format: miniquad::TextureFormat,
+        image: &Image,
+    ) -> miniquad::TextureId {
+        ctx.new_texture_from_data_and_format(
+            &image.bytes,
+            miniquad::TextureParams {
+                format,
+                width: image.width as u32,
+                height: image.height as u32,
+                ..Default::default()
+            },
+        )
+    }
+
+    pub fn format(&self) -> miniquad::TextureFormat {
+        self.format
+    }
+
     pub fn new_unique_id(&mut self) -> SpriteKey {
         self.unique_id += 1;
 
@@ -96,11 +174,7 @@ impl Atlas {
             if texture_width != self.image.width as _ || texture_height != self.image.height as _ {
                 ctx.delete_texture(self.texture);
 
-                self.texture = ctx.new_texture_from_rgba8(
-                    self.image.width,
-                    self.image.height,
-                    &self.image.bytes[..],
-                );
+                self.texture = Self::new_texture(ctx, self.format, &self.image);
                 ctx.texture_set_filter(self.texture, self.filter, miniquad::MipmapFilterMode::None);
             }
 
@@ -123,28 +197,113 @@ impl Atlas {
         })
     }
 
-    pub fn cache_sprite(&mut self, key: SpriteKey, sprite: Image) {
-        let (width, height) = (sprite.width as usize, sprite.height as usize);
+    /// Changes whenever sprites already in the atlas are moved, such as when it
+    /// grows or is cleared, so positions looked up before then are out of date.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
 
-        let x = if self.cursor_x + (width as u16) < self.image.width {
-            if height as u16 > self.max_line_height {
-                self.max_line_height = height as u16;
-            }
-            let res = self.cursor_x + Self::GAP;
-            self.cursor_x += width as u16 + Self::GAP * 2;
-            res
+    /// Removes all sprites and shrinks the atlas back to its initial size.
+    pub fn clear(&mut self) {
+        self.remove_sprites(|_| true);
+    }
+
+    /// Removes every sprite for which `predicate` returns true, then repacks the
+    /// remaining ones into an atlas of the smallest size they fit in.
+    pub fn remove_sprites<F: FnMut(&SpriteKey) -> bool>(&mut self, mut predicate: F) {
+        let sprites = self
+            .sprites
+            .drain()
+            .filter(|(key, _)| !predicate(key))
+            .collect::<Vec<_>>();
+        self.cursor_x = 0;
+        self.cursor_y = 0;
+        self.max_line_height = 0;
+        self.dirty = true;
+        self.full = false;
+        self.generation += 1;
+
+        let old_image = std::mem::replace(
+            &mut self.image,
+            Self::blank_image(self.format, Self::INITIAL_SIZE, Self::INITIAL_SIZE),
+        );
+
+        for (key, sprite) in sprites {
+            let image = self.sub_image(&old_image, sprite.rect);
+            // these all fitted before, and will again once the atlas has grown
+            let _ = self.cache_sprite(key, image);
+        }
+    }
+
+    /// Overwrites the pixels of an existing sprite, starting `x` and `y` pixels
+    /// from its top left corner, with `sprite`. Returns false, leaving the
+    /// atlas unchanged, if there is no such sprite or `sprite` doesn't fit
+    /// within it.
+    pub fn update_sprite(&mut self, key: SpriteKey, x: u32, y: u32, sprite: &Image) -> bool {
+        let rect = match self.sprites.get(&key) {
+            Some(existing) => existing.rect,
+            None => return false,
+        };
+        if x + sprite.width as u32 > rect.w as u32 || y + sprite.height as u32 > rect.h as u32 {
+            return false;
+        }
+
+        self.dirty = true;
+        self.copy_pixels(rect.x as u32 + x, rect.y as u32 + y, sprite);
+        true
+    }
+
+    // the pixel helpers on Image assume RGBA, so these work on raw bytes instead
+    fn copy_pixels(&mut self, x: u32, y: u32, sprite: &Image) {
+        let bpp = Self::bytes_per_pixel(self.format);
+        let row = sprite.width as usize * bpp;
+        for j in 0..sprite.height as usize {
+            let dst = ((y as usize + j) * self.image.width as usize + x as usize) * bpp;
+            self.image.bytes[dst..dst + row].copy_from_slice(&sprite.bytes[j * row..(j + 1) * row]);
+        }
+    }
+
+    fn sub_image(&self, image: &Image, rect: Rect) -> Image {
+        let bpp = Self::bytes_per_pixel(self.format);
+        let (width, height) = (rect.w as usize, rect.h as usize);
+        let mut bytes = Vec::with_capacity(width * height * bpp);
+        for y in rect.y as usize..rect.y as usize + height {
+            let src = (y * image.width as usize + rect.x as usize) * bpp;
+            bytes.extend_from_slice(&image.bytes[src..src + width * bpp]);
+        }
+        Image {
+            bytes,
+            width: width as u16,
+            height: height as u16,
+        }
+    }
+
+    /// Adds `sprite` to the atlas, doubling the atlas size as many times as needed to fit it.
+    /// Fails, leaving the atlas unchanged, if that would take the atlas beyond `MAX_SIZE`.
+    /// `sprite` holds pixels in the atlas's [Atlas::format].
+    pub fn cache_sprite(&mut self, key: SpriteKey, sprite: Image) -> Result<(), AtlasFull> {
+        let (width, height) = (sprite.width, sprite.height);
+
+        let new_line = self.cursor_x as u32 + width as u32 >= self.image.width as u32;
+        let (x, y) = if new_line {
+            (Self::GAP, self.cursor_y + self.max_line_height + Self::GAP * 2)
         } else {
-            self.cursor_y += self.max_line_height + Self::GAP * 2;
-            self.cursor_x = width as u16 + Self::GAP;
-            self.max_line_height = height as u16;
-            Self::GAP
+            (self.cursor_x + Self::GAP, self.cursor_y)
         };
-        let y = self.cursor_y;
 
         // texture bounds exceeded
-        if self.cursor_y + height as u16 > self.image.height {
+        if x as u32 + width as u32 > self.image.width as u32
+            || y as u32 + height as u32 > self.image.height as u32
+        {
+            if self.image.width as u32 * 2 > Self::MAX_SIZE as u32
+                || self.image.height as u32 * 2 > Self::MAX_SIZE as u32
+            {
+                return Err(AtlasFull);
+            }
+
             // reset glyph cache state
             let sprites = self.sprites.drain().collect::<Vec<_>>();
+            self.generation += 1;
             self.cursor_x = 0;
             self.cursor_y = 0;
             self.max_line_height = 0;
@@ -152,39 +311,39 @@ impl Atlas {
             let old_image = self.image.clone();
 
             // increase font texture size
-            self.image = Image::gen_image_color(
-                self.image.width * 2,
-                self.image.height * 2,
-                Color::from_rgba(0.0, 0.0, 0.0, 0.0),
-            );
+            self.image =
+                Self::blank_image(self.format, self.image.width * 2, self.image.height * 2);
 
-            // recache all previously cached symbols
+            // recache all previously cached symbols, they fit in the smaller atlas
+            // so will fit in this one
             for (key, sprite) in sprites {
-                let image = old_image.sub_image(sprite.rect);
-                self.cache_sprite(key, image);
+                let image = self.sub_image(&old_image, sprite.rect);
+                let _ = self.cache_sprite(key, image);
             }
 
             // cache the new sprite
-            self.cache_sprite(key, sprite);
-        } else {
-            self.dirty = true;
-
-            for j in 0..height {
-                for i in 0..width {
-                    self.image.set_pixel(
-                        x as u32 + i as u32,
-                        y as u32 + j as u32,
-                        sprite.get_pixel(i as u32, j as u32),
-                    );
-                }
-            }
+            return self.cache_sprite(key, sprite);
+        }
 
-            self.sprites.insert(
-                key,
-                Sprite {
-                    rect: Rect::new(x as f32, y as f32, width as f32, height as f32),
-                },
-            );
+        if new_line {
+            self.cursor_y = y;
+            self.cursor_x = width + Self::GAP;
+            self.max_line_height = height;
+        } else {
+            self.cursor_x += width + Self::GAP * 2;
+            self.max_line_height = self.max_line_height.max(height);
         }
+
+        self.dirty = true;
+        self.copy_pixels(x as u32, y as u32, &sprite);
+
+        self.sprites.insert(
+            key,
+            Sprite {
+                rect: Rect::new(x as f32, y as f32, width as f32, height as f32),
+            },
+        );
+
+        Ok(())
     }
 }
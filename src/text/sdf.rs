@@ -0,0 +1,122 @@
+//! Signed distance field generation for glyph bitmaps.
+
+/// Font size glyphs are rasterized at before being turned into distance fields.
+pub const SDF_FONT_SIZE: u16 = 48;
+
+/// Distance in pixels, at `SDF_FONT_SIZE`, over which the field fades from fully
+/// inside to fully outside a glyph. Glyph bitmaps are padded by this much on every
+/// side.
+pub const SPREAD: u16 = 6;
+
+/// Turns a coverage bitmap into a signed distance field, padded by `SPREAD` pixels
+/// on each side. Returns the field and its width and height.
+///
+/// Values are 0.5 (128) on the glyph's edge, increasing towards 1.0 inside the
+/// glyph and decreasing towards 0.0 outside it.
+pub fn signed_distance_field(coverage: &[u8], width: u16, height: u16) -> (Vec<u8>, u16, u16) {
+    let (padded_width, padded_height) = (width + SPREAD * 2, height + SPREAD * 2);
+
+    let mut inside = vec![false; padded_width as usize * padded_height as usize];
+    for y in 0..height as usize {
+        for x in 0..width as usize {
+            let padded = (y + SPREAD as usize) * padded_width as usize + x + SPREAD as usize;
+            inside[padded] = coverage[y * width as usize + x] >= 128;
+        }
+    }
+
+    let outside = inside.iter().map(|inside| !inside).collect::<Vec<_>>();
+    let distance_outside = distance_to(&inside, padded_width, padded_height);
+    let distance_inside = distance_to(&outside, padded_width, padded_height);
+
+    let field = inside
+        .iter()
+        .zip(distance_outside.iter().zip(&distance_inside))
+        .map(|(inside, (distance_outside, distance_inside))| {
+            // distances are between pixel centers, the edge lies half way
+            let distance = if *inside {
+                -(distance_inside - 0.5)
+            } else {
+                distance_outside - 0.5
+            };
+            let value = 0.5 - distance / (SPREAD as f32 * 2.);
+            (value.clamp(0., 1.) * 255.).round() as u8
+        })
+        .collect();
+
+    (field, padded_width, padded_height)
+}
+
+/// Distance from each pixel to the nearest pixel set in `target`, using two
+/// passes of dead reckoning over the 8-neighbourhood.
+fn distance_to(target: &[bool], width: u16, height: u16) -> Vec<f32> {
+    let (width, height) = (width as i32, height as i32);
+    let index = |x: i32, y: i32| (y * width + x) as usize;
+
+    let mut nearest = vec![(0i32, 0i32); target.len()];
+    let mut distance = vec![f32::INFINITY; target.len()];
+
+    for y in 0..height {
+        for x in 0..width {
+            if target[index(x, y)] {
+                nearest[index(x, y)] = (x, y);
+                distance[index(x, y)] = 0.;
+            }
+        }
+    }
+
+    let mut propagate = |x: i32, y: i32, neighbours: &[(i32, i32)]| {
+        for (dx, dy) in neighbours {
+            let (nx, ny) = (x + dx, y + dy);
+            if nx < 0 || ny < 0 || nx >= width || ny >= height {
+                continue;
+            }
+            if distance[index(nx, ny)] == f32::INFINITY {
+                continue;
+            }
+
+            let candidate = nearest[index(nx, ny)];
+            let candidate_distance =
+                (((candidate.0 - x).pow(2) + (candidate.1 - y).pow(2)) as f32).sqrt();
+            if candidate_distance < distance[index(x, y)] {
+                nearest[index(x, y)] = candidate;
+                distance[index(x, y)] = candidate_distance;
+            }
+        }
+    };
+
+    for y in 0..height {
+        for x in 0..width {
+            propagate(x, y, &[(-1, -1), (0, -1), (1, -1), (-1, 0)]);
+        }
+    }
+    for y in (0..height).rev() {
+        for x in (0..width).rev() {
+            propagate(x, y, &[(1, 0), (-1, 1), (0, 1), (1, 1)]);
+        }
+    }
+
+    distance
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signed_distance_field() {
+        // a 4x4 filled square
+        let (field, width, height) = signed_distance_field(&[255; 16], 4, 4);
+        assert_eq!((width, height), (4 + SPREAD * 2, 4 + SPREAD * 2));
+
+        let at = |x: u16, y: u16| field[(y * width + x) as usize];
+        let center = SPREAD + 1;
+
+        // inside is above the edge value, outside below it
+        assert!(at(center, center) > 128);
+        assert!(at(SPREAD - 1, center) < 128);
+        // the two pixels either side of the edge straddle it evenly
+        assert_eq!(at(SPREAD, center) as i32 - 128, 127 - at(SPREAD - 1, center) as i32);
+        // far outside is fully empty
+        assert_eq!(at(0, 0), 0);
+    }
+}
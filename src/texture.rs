@@ -2,6 +2,7 @@
 
 use crate::{
     Color,
+    dimen::UVec2,
     math::Rect,
     text::atlas::SpriteKey, 
     //Error,
@@ -14,6 +15,7 @@ use glam::{vec2, Vec2};
 pub use crate::quad_gl::FilterMode;
 
 use slotmap::SlotMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
 slotmap::new_key_type! {
@@ -152,6 +154,58 @@ impl Image {
         }
     }
 
+    /// Creates an opaque greyscale Image of white noise, where every pixel is an
+    /// independent random shade. The same `seed` always gives the same image.
+    pub fn gen_image_noise(width: u16, height: u16, seed: u64) -> Image {
+        Self::gen_image_from_fn(width, height, |x, y| noise_hash(x as i64, y as i64, seed))
+    }
+
+    /// Creates an opaque greyscale Image of smooth value noise, with features roughly
+    /// `cell_size` pixels across. The same `seed` always gives the same image.
+    ///
+    /// With more than one octave, finer layers of noise at half the size and half the
+    /// strength of the previous one are added on top (fractal Brownian motion), giving
+    /// a cloudy, more natural look.
+    pub fn gen_image_value_noise(
+        width: u16,
+        height: u16,
+        cell_size: f32,
+        octaves: u32,
+        seed: u64,
+    ) -> Image {
+        assert!(cell_size > 0., "cell_size must be positive");
+
+        Self::gen_image_from_fn(width, height, |x, y| {
+            let (mut total, mut total_amplitude) = (0., 0.);
+            let (mut frequency, mut amplitude) = (1. / cell_size, 1.);
+
+            for octave in 0..octaves.max(1) {
+                let seed = seed.wrapping_add(octave as u64);
+                total += value_noise(x as f32 * frequency, y as f32 * frequency, seed) * amplitude;
+                total_amplitude += amplitude;
+                frequency *= 2.;
+                amplitude *= 0.5;
+            }
+
+            total / total_amplitude
+        })
+    }
+
+    fn gen_image_from_fn(width: u16, height: u16, shade: impl Fn(u16, u16) -> f32) -> Image {
+        let mut bytes = Vec::with_capacity(width as usize * height as usize * 4);
+        for y in 0..height {
+            for x in 0..width {
+                let value = (shade(x, y) * 255.).round() as u8;
+                bytes.extend_from_slice(&[value, value, value, 255]);
+            }
+        }
+        Image {
+            width,
+            height,
+            bytes,
+        }
+    }
+
     /// Updates this image from a slice of [Color]s.
     pub fn update(&mut self, colors: &[Color]) {
         assert!(self.width as usize * self.height as usize == colors.len());
@@ -174,6 +228,29 @@ impl Image {
         self.height as usize
     }
 
+    /// Returns the width and height of this image.
+    pub fn size(&self) -> UVec2 {
+        UVec2::new(self.width as u32, self.height as u32)
+    }
+
+    /// Returns an iterator over every pixel in this image, as `(x, y, color)`, row by
+    /// row from the top left.
+    ///
+    /// ```
+    /// # use milliquad::image::Image;
+    /// # use milliquad::color::Color;
+    /// let image = Image::gen_image_color(2, 2, Color::WHITE);
+    /// let opaque = image.pixels().filter(|(_, _, color)| color.a() == 1.0).count();
+    /// # assert_eq!(opaque, 4);
+    /// ```
+    pub fn pixels(&self) -> impl Iterator<Item = (u32, u32, Color)> + '_ {
+        let width = self.width as u32;
+        self.get_image_data()
+            .iter()
+            .enumerate()
+            .map(move |(i, pixel)| (i as u32 % width, i as u32 / width, Color::from(*pixel)))
+    }
+
     /// Returns this image's data as a slice of 4-byte arrays.
     pub fn get_image_data(&self) -> &[[u8; 4]] {
         use std::slice;
@@ -198,16 +275,29 @@ impl Image {
         }
     }
 
-    /// Modifies a pixel [Color] in this image.
+    /// Modifies a pixel [Color] in this image. Does nothing if `x` or `y` is
+    /// outside the image.
     pub fn set_pixel(&mut self, x: u32, y: u32, color: Color) {
-        let width = self.width;
+        if let Some(index) = self.pixel_index(x, y) {
+            self.get_image_data_mut()[index] = color.into();
+        }
+    }
 
-        self.get_image_data_mut()[(y * width as u32 + x) as usize] = color.into();
+    /// Returns a pixel [Color] from this image, or `None` if `x` or `y` is
+    /// outside the image.
+    pub fn get_pixel(&self, x: u32, y: u32) -> Option<Color> {
+        self.pixel_index(x, y)
+            .map(|index| self.get_image_data()[index].into())
     }
 
-    /// Returns a pixel [Color] from this image.
-    pub fn get_pixel(&self, x: u32, y: u32) -> Color {
-        self.get_image_data()[(y * self.width as u32 + x) as usize].into()
+    // without the bounds check, an `x` past the right edge would wrap around
+    // onto the next row
+    fn pixel_index(&self, x: u32, y: u32) -> Option<usize> {
+        if x < self.width as u32 && y < self.height as u32 {
+            Some(y as usize * self.width as usize + x as usize)
+        } else {
+            None
+        }
     }
 
     /// Returns an Image from a rect inside this image.
@@ -306,6 +396,69 @@ impl Image {
         }
     }
 
+    /// Returns a copy of this image convolved with a 3x3 `kernel`, which is indexed as
+    /// `kernel[row][column]` and centered on each pixel. Pixels beyond the edges of the image are
+    /// treated as repeating the nearest edge pixel. Only the color is filtered, the alpha of each
+    /// pixel is unchanged.
+    ///
+    /// For example, `[[1. / 9.; 3]; 3]` blurs the image, and
+    /// `[[0., -1., 0.], [-1., 5., -1.], [0., -1., 0.]]` sharpens it.
+    pub fn apply_kernel(&self, kernel: &[[f32; 3]; 3]) -> Image {
+        let (width, height) = (self.width as i32, self.height as i32);
+        let mut result = self.clone();
+
+        for y in 0..height {
+            for x in 0..width {
+                let mut sum = [0.; 3];
+                for (row, weights) in kernel.iter().enumerate() {
+                    for (column, weight) in weights.iter().enumerate() {
+                        let sample_x = (x + column as i32 - 1).clamp(0, width - 1);
+                        let sample_y = (y + row as i32 - 1).clamp(0, height - 1);
+                        let i = (sample_y * width + sample_x) as usize * 4;
+                        for (sum, value) in sum.iter_mut().zip(&self.bytes[i..i + 3]) {
+                            *sum += *value as f32 * weight;
+                        }
+                    }
+                }
+
+                let i = (y * width + x) as usize * 4;
+                for (value, sum) in result.bytes[i..i + 3].iter_mut().zip(sum) {
+                    *value = sum.round().clamp(0., 255.) as u8;
+                }
+            }
+        }
+
+        result
+    }
+
+    /// Converts every pixel in this image to a shade of grey of the same perceived brightness.
+    pub fn grayscale(&mut self) {
+        for pixel in self.get_image_data_mut() {
+            let color = Color::from(*pixel);
+            let grey = color.subjective_brightness();
+            *pixel = Color::from_rgba(grey, grey, grey, color.a).into();
+        }
+    }
+
+    /// Inverts the color of every pixel in this image, leaving alpha unchanged.
+    pub fn invert(&mut self) {
+        for pixel in self.get_image_data_mut() {
+            for channel in &mut pixel[0..3] {
+                *channel = 255 - *channel;
+            }
+        }
+    }
+
+    /// Multiplies the color of every pixel in this image by `factor`, leaving alpha unchanged.
+    /// Factors below `1.0` darken the image and factors above it brighten it.
+    pub fn adjust_brightness(&mut self, factor: f32) {
+        for pixel in self.get_image_data_mut() {
+            for channel in &mut pixel[0..3] {
+                *channel = (*channel as f32 * factor).round().clamp(0., 255.) as u8;
+            }
+        }
+    }
+
     /// Saves this image as a PNG file.
     /// This method is not supported on web and will panic.
     pub fn export_png(&self, path: &str) {
@@ -437,6 +590,11 @@ pub struct DrawTextureParams {
     /// E.g. pivot (0,0) rotates around the top left corner of the screen, not of the
     /// texture.
     pub pivot: Option<Vec2>,
+
+    /// Draw straight from the texture instead of from the texture batcher's atlas.
+    /// Textures larger than the batcher accepts are always drawn this way.
+    /// Is false by default
+    pub bypass_batcher: bool,
 }
 
 impl Default for DrawTextureParams {
@@ -448,6 +606,7 @@ impl Default for DrawTextureParams {
             pivot: None,
             flip_x: false,
             flip_y: false,
+            bypass_batcher: false,
         }
     }
 }
@@ -488,9 +647,12 @@ pub fn draw_texture_ex(
         h: height,
     });
 
-    let texture_opt =
-        texture_batcher
-        .get(quad_context, textures, gl, texture)
+    let batched = if params.bypass_batcher {
+        None
+    } else {
+        texture_batcher.get(quad_context, textures, gl, texture)
+    };
+    let texture_opt = batched
         .map(|(batched_texture, uv)| {
             let [batched_width, batched_height] = batched_texture.size(quad_context, textures, gl).to_array();
             sx = ((sx / width) * uv.w + uv.x) * batched_width;
@@ -561,6 +723,145 @@ pub fn draw_texture_ex(
     gl.geometry(&vertices, &indices);
 }
 
+/// Draws many parts of one texture, each given as `(source, dest, color)`: the `source`
+/// rectangle in texture pixels is stretched over the `dest` rectangle and tinted by `color`.
+/// The texture is bound once for all of them, and the quads are submitted in large batches,
+/// so they are drawn in as few draw calls as possible.
+pub fn draw_texture_multi(
+    gl: &mut QuadGl,
+    quad_context: &mut dyn miniquad::RenderingBackend,
+    textures: &TexturesContext,
+    texture_batcher: &mut Batcher,
+    texture: &Texture2D,
+    quads: impl IntoIterator<Item = (Rect, Rect, Color)>,
+) {
+    // keep each batch of geometry within the draw call index limit
+    const MAX_QUADS: usize = 800;
+
+    let size = texture.size(quad_context, textures, gl);
+
+    // the uv rect of the texture within whatever is actually bound
+    let batched = texture_batcher.get(quad_context, textures, gl, texture);
+    let (texture, uv) = match &batched {
+        Some((batched_texture, uv)) => (batched_texture, *uv),
+        None => (texture, Rect::new(0., 0., 1., 1.)),
+    };
+
+    gl.texture(textures, Some(texture));
+    gl.draw_mode(DrawMode::Triangles);
+
+    let mut vertices = Vec::with_capacity(MAX_QUADS * 4);
+    let mut indices = Vec::with_capacity(MAX_QUADS * 6);
+
+    for (source, dest, color) in quads {
+        let left = uv.x + source.x / size.x * uv.w;
+        let top = uv.y + source.y / size.y * uv.h;
+        let right = uv.x + (source.x + source.w) / size.x * uv.w;
+        let bottom = uv.y + (source.y + source.h) / size.y * uv.h;
+
+        let base = vertices.len() as u16;
+        #[rustfmt::skip]
+        vertices.extend_from_slice(&[
+            Vertex::new(dest.x,          dest.y,          0., left,  top,    color),
+            Vertex::new(dest.x + dest.w, dest.y,          0., right, top,    color),
+            Vertex::new(dest.x + dest.w, dest.y + dest.h, 0., right, bottom, color),
+            Vertex::new(dest.x,          dest.y + dest.h, 0., left,  bottom, color),
+        ]);
+        indices.extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3]);
+
+        if indices.len() == MAX_QUADS * 6 {
+            gl.geometry(&vertices, &indices);
+            vertices.clear();
+            indices.clear();
+        }
+    }
+
+    if !indices.is_empty() {
+        gl.geometry(&vertices, &indices);
+    }
+}
+
+/// Fills `dest` with copies of the texture, each drawn at `tile_size`, starting
+/// from the top left corner of `dest`. Tiles on the right and bottom edges are
+/// cut off. Nothing is drawn unless both sides of `tile_size` are positive.
+/// Draws straight from the texture with its wrap mode set to repeat, which stays
+/// set afterwards, as the previous mode can't be queried, so later draws of the
+/// texture repeat too. Textures with non power of two sizes only repeat on WebGL 2.
+pub fn draw_texture_tiled(
+    gl: &mut QuadGl,
+    quad_context: &mut dyn miniquad::RenderingBackend,
+    textures: &TexturesContext,
+    texture: &Texture2D,
+    dest: Rect,
+    tile_size: Vec2,
+    color: Color,
+) {
+    // also rejects NaN
+    if !(tile_size.x > 0. && tile_size.y > 0.) {
+        return;
+    }
+
+    let raw_texture = texture.raw_miniquad_id(textures, gl);
+    quad_context.texture_set_wrap(
+        raw_texture,
+        miniquad::TextureWrap::Repeat,
+        miniquad::TextureWrap::Repeat,
+    );
+
+    let (u, v) = (dest.w / tile_size.x, dest.h / tile_size.y);
+
+    #[rustfmt::skip]
+    let vertices = [
+        Vertex::new(dest.x         , dest.y         , 0., 0., 0., color),
+        Vertex::new(dest.x + dest.w, dest.y         , 0., u , 0., color),
+        Vertex::new(dest.x + dest.w, dest.y + dest.h, 0., u , v , color),
+        Vertex::new(dest.x         , dest.y + dest.h, 0., 0., v , color),
+    ];
+    let indices: [u16; 6] = [0, 1, 2, 0, 2, 3];
+
+    gl.texture(textures, Some(texture));
+    gl.draw_mode(DrawMode::Triangles);
+    gl.geometry(&vertices, &indices);
+}
+
+/// Draws the texture onto an arbitrary quad, given as `(position, uv)` pairs for each
+/// of its corners.
+/// UVs are normalized (0..1 spans the whole texture) and must stay within that
+/// range, as the texture may be drawn from the batcher's atlas.
+pub fn draw_texture_quad(
+    gl: &mut QuadGl,
+    quad_context: &mut dyn miniquad::RenderingBackend,
+    textures: &TexturesContext,
+    texture_batcher: &mut Batcher,
+    texture: &Texture2D,
+    corners: [(Vec2, Vec2); 4],
+    color: Color,
+) {
+    let uvs = corners.map(|(_, uv)| uv);
+    let corners = corners.map(|(position, _)| position);
+    let batched = texture_batcher.get(quad_context, textures, gl, texture);
+    let (texture, uvs) = match batched {
+        Some((batched_texture, uv)) => {
+            let uvs = uvs.map(|p| vec2(uv.x + p.x * uv.w, uv.y + p.y * uv.h));
+            (batched_texture, uvs)
+        }
+        None => (texture.clone(), uvs),
+    };
+
+    #[rustfmt::skip]
+    let vertices = [
+        Vertex::new(corners[0].x, corners[0].y, 0., uvs[0].x, uvs[0].y, color),
+        Vertex::new(corners[1].x, corners[1].y, 0., uvs[1].x, uvs[1].y, color),
+        Vertex::new(corners[2].x, corners[2].y, 0., uvs[2].x, uvs[2].y, color),
+        Vertex::new(corners[3].x, corners[3].y, 0., uvs[3].x, uvs[3].y, color),
+    ];
+    let indices: [u16; 6] = [0, 1, 2, 0, 2, 3];
+
+    gl.texture(textures, Some(&texture));
+    gl.draw_mode(DrawMode::Triangles);
+    gl.geometry(&vertices, &indices);
+}
+
 /// Get pixel data from screen buffer and return an Image (screenshot)
 pub fn get_screen_data(
     quad_context: &mut dyn miniquad::RenderingBackend, 
@@ -913,13 +1214,25 @@ impl Texture2D {
 pub(crate) struct Batcher {
     unbatched: Vec<Texture2D>,
     atlas: crate::text::atlas::Atlas,
+    // batcher "time" each atlas entry was last drawn at, for LRU eviction
+    last_used: HashMap<SpriteKey, u64>,
+    clock: u64,
 }
 
 impl Batcher {
+    /// Once the atlas grows beyond this size in either dimension, the least
+    /// recently drawn textures are evicted from it.
+    const MAX_ATLAS_SIZE: u16 = 4096;
+    /// Textures larger than this in either dimension are never added to the
+    /// atlas; they would take up most of it and gain little from batching.
+    const MAX_SPRITE_SIZE: f32 = 1024.;
+
     pub fn new(ctx: &mut dyn miniquad::RenderingBackend) -> Batcher {
         Batcher {
             unbatched: vec![],
             atlas: crate::text::atlas::Atlas::new(ctx, miniquad::FilterMode::Linear),
+            last_used: HashMap::new(),
+            clock: 0,
         }
     }
 
@@ -937,8 +1250,118 @@ impl Batcher {
     {
         let id = SpriteKey::Texture(texture.raw_miniquad_id(textures, gl));
         let uv_rect = self.atlas.get_uv_rect(quad_context, id)?;
+        self.touch(id);
         Some((Texture2D::unmanaged(self.atlas.texture(quad_context)), uv_rect))
     }
+
+    /// Removes every texture from the atlas and releases the space it used.
+    /// Textures drawn afterwards are drawn directly until the atlas is built
+    /// again.
+    pub fn clear(&mut self) {
+        self.atlas.clear();
+        self.last_used.clear();
+    }
+
+    /// Forgets a texture which is about to be deleted, so that a texture later
+    /// created with the same id doesn't pick up its atlas entry.
+    pub fn remove(&mut self, texture: miniquad::TextureId) {
+        self.unbatched
+            .retain(|unbatched| unbatched.texture != TextureHandle::Unmanaged(texture));
+
+        let id = SpriteKey::Texture(texture);
+        if self.last_used.remove(&id).is_some() {
+            self.atlas.remove_sprites(|sprite| *sprite == id);
+        }
+    }
+
+    /// Keeps the atlas copy of a texture in step with new contents uploaded to
+    /// the texture itself, at the given offset.
+    pub fn update(&mut self, texture: miniquad::TextureId, x: u32, y: u32, image: &Image) {
+        let id = SpriteKey::Texture(texture);
+        if self.atlas.get(id).is_some() && !self.atlas.update_sprite(id, x, y, image) {
+            self.last_used.remove(&id);
+            self.atlas.remove_sprites(|sprite| *sprite == id);
+        }
+    }
+
+    pub fn set_filter(&mut self, ctx: &mut dyn miniquad::RenderingBackend, filter_mode: FilterMode) {
+        self.atlas.set_filter(ctx, filter_mode);
+    }
+
+    /// Adds any textures loaded since the atlas was last built, then repacks the
+    /// whole atlas so space left behind by evicted textures is reclaimed.
+    pub fn rebuild(
+        &mut self,
+        quad_context: &mut dyn miniquad::RenderingBackend,
+        textures: &TexturesContext,
+        gl: &QuadGl,
+    ) {
+        build_textures_atlas(quad_context, textures, gl, self);
+        self.atlas.remove_sprites(|_| false);
+    }
+
+    fn touch(&mut self, id: SpriteKey) {
+        self.clock += 1;
+        self.last_used.insert(id, self.clock);
+    }
+
+    fn cache(&mut self, id: SpriteKey, sprite: Image) {
+        // textures which don't fit are drawn directly instead
+        if self.atlas.cache_sprite(id, sprite).is_err() {
+            return;
+        }
+        self.touch(id);
+
+        // evict the least recently used half of the atlas and repack,
+        // until it is back within the size limit
+        while self.atlas.width() > Self::MAX_ATLAS_SIZE || self.atlas.height() > Self::MAX_ATLAS_SIZE {
+            let mut entries = self.last_used.iter().map(|(id, time)| (*time, *id)).collect::<Vec<_>>();
+            if entries.is_empty() {
+                break;
+            }
+            entries.sort_unstable_by_key(|(time, _)| *time);
+
+            let evicted = entries[..entries.len().div_ceil(2)]
+                .iter()
+                .map(|(_, id)| *id)
+                .collect::<HashSet<_>>();
+            for id in &evicted {
+                self.last_used.remove(id);
+            }
+            self.atlas.remove_sprites(|id| evicted.contains(id));
+        }
+    }
+}
+
+/// A pseudo-random value in `0.0..=1.0` for an integer grid point.
+fn noise_hash(x: i64, y: i64, seed: u64) -> f32 {
+    // splitmix64 finalizer over the combined coordinates
+    let mut hash = seed
+        ^ (x as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+        ^ (y as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F);
+    hash ^= hash >> 30;
+    hash = hash.wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    hash ^= hash >> 27;
+    hash = hash.wrapping_mul(0x94D0_49BB_1331_11EB);
+    hash ^= hash >> 31;
+
+    (hash >> 40) as f32 / ((1 << 24) - 1) as f32
+}
+
+/// Smoothly interpolates between the random values at the grid points around `(x, y)`.
+fn value_noise(x: f32, y: f32, seed: u64) -> f32 {
+    let (x0, y0) = (x.floor(), y.floor());
+    let smooth = |t: f32| t * t * (3. - 2. * t);
+    let (tx, ty) = (smooth(x - x0), smooth(y - y0));
+    let (x0, y0) = (x0 as i64, y0 as i64);
+
+    let top = lerp(noise_hash(x0, y0, seed), noise_hash(x0 + 1, y0, seed), tx);
+    let bottom = lerp(noise_hash(x0, y0 + 1, seed), noise_hash(x0 + 1, y0 + 1, seed), tx);
+    lerp(top, bottom, ty)
+}
+
+fn lerp(from: f32, to: f32, t: f32) -> f32 {
+    from + (to - from) * t
 }
 
 /// Build an atlas out of all currently loaded texture
@@ -953,11 +1376,16 @@ pub fn build_textures_atlas(
     texture_batcher: &mut Batcher
     )
 {
-    for texture in texture_batcher.unbatched.drain(0..) {
+    for texture in std::mem::take(&mut texture_batcher.unbatched) {
+        let size = texture.size(quad_context, textures, gl);
+        if size.x > Batcher::MAX_SPRITE_SIZE || size.y > Batcher::MAX_SPRITE_SIZE {
+            continue;
+        }
+
         let sprite: Image = texture.get_texture_data(quad_context, textures, gl);
         let id = SpriteKey::Texture(texture.raw_miniquad_id(textures, gl));
 
-        texture_batcher.atlas.cache_sprite(id, sprite);
+        texture_batcher.cache(id, sprite);
     }
 
     let texture = texture_batcher.atlas.texture(quad_context);
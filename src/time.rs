@@ -17,18 +17,39 @@
 /// Measures the amount of time elapsed since its creation.
 pub struct Stopwatch
 {
-    start: f64
+    start: f64,
+    lap_start: f64
+}
+
+impl Default for Stopwatch
+{
+    fn default() -> Self
+    {
+        Self::new()
+    }
 }
 
 impl Stopwatch
 {
     /// Creates a new Stopwatch, starting at the current time.
     #[inline]
-    pub fn new() -> Result<Self, i32> // TODO just return Self, no Result
+    pub fn new() -> Self
     {
         let start = miniquad::date::now();
 
-        Ok(Self { start })
+        Self {
+            start,
+            lap_start: start
+        }
+    }
+
+    /// Creates a new Stopwatch, starting at the current time. This never
+    /// fails.
+    #[deprecated(note = "Stopwatch::new can't fail, use it instead")]
+    #[inline]
+    pub fn new_checked() -> Result<Self, i32>
+    {
+        Ok(Self::new())
     }
 
     /// Returns the number of seconds since the Stopwatch was created.
@@ -37,5 +58,28 @@ impl Stopwatch
     {
         miniquad::date::now() - self.start
     }
+
+    /// Returns the number of seconds since the Stopwatch was created, as an
+    /// `f32`.
+    #[inline]
+    pub fn secs_elapsed_f32(&self) -> f32
+    {
+        self.secs_elapsed() as f32
+    }
+
+    /// Returns the number of seconds since the previous call to `lap()`, or
+    /// since the Stopwatch was created if this is the first lap, and starts
+    /// a new lap.
+    ///
+    /// This does not affect the value returned by
+    /// [Stopwatch::secs_elapsed].
+    #[inline]
+    pub fn lap(&mut self) -> f64
+    {
+        let now = miniquad::date::now();
+        let lap = now - self.lap_start;
+        self.lap_start = now;
+        lap
+    }
 }
 
@@ -21,6 +21,7 @@ use std::rc::Rc;
 use std::cell::RefCell;
 use crate::dimen::{IVec2, UVec2, Vec2};
 use crate::error::{BacktraceError, ErrorMessage};
+use crate::image::{CaptureAlpha, RawBitmapData};
 use crate::{GLRenderer, Graphics2D};
 
 pub use miniquad::KeyCode;
@@ -95,7 +96,10 @@ pub enum WindowCreationError
     /// Failed to make the graphics context current.
     MakeContextCurrentFailed,
     /// Failed to instantiate the renderer.
-    RendererCreationFailed
+    RendererCreationFailed,
+    /// A window has already been run in this process. Only one window, and
+    /// one event loop, is supported per process.
+    WindowAlreadyRunning
 }
 
 impl Display for WindowCreationError
@@ -115,6 +119,9 @@ impl Display for WindowCreationError
             WindowCreationError::RendererCreationFailed => {
                 f.write_str("Failed to create the renderer")
             }
+            WindowCreationError::WindowAlreadyRunning => {
+                f.write_str("A window has already been run in this process")
+            }
         }
     }
 }
@@ -122,6 +129,9 @@ impl Display for WindowCreationError
 /// A set of callbacks for an active window. If a callback is not implemented,
 /// it will do nothing by default, so it is only necessary to implement the
 /// callbacks you actually need.
+///
+/// Gamepad input is not delivered to any of these callbacks, as miniquad 0.4
+/// does not report gamepad events.
 pub trait WindowHandler<UserEventType = ()>
 {
     /// Invoked once when the window first starts.
@@ -135,6 +145,11 @@ pub trait WindowHandler<UserEventType = ()>
     {
     }
 
+    /// Invoked once per frame, before [WindowHandler::on_draw].
+    ///
+    /// With a fixed update rate set using [WindowHelper::set_fixed_update],
+    /// this is instead invoked as many times as needed to keep up with that
+    /// rate, which may be zero or several times in a given frame.
     #[allow(unused_variables)]
     #[inline]
     fn on_update(
@@ -207,6 +222,13 @@ pub trait WindowHandler<UserEventType = ()>
     ///
     /// It is possible to request a redraw from any callback using
     /// [WindowHelper::request_redraw].
+    ///
+    /// Every frame is presented once this returns, even if nothing changed,
+    /// and the window contents from earlier frames aren't preserved, so the
+    /// whole window must be drawn each time. miniquad swaps the buffers after
+    /// every frame, so there is no way to skip presenting one. To avoid
+    /// drawing identical frames, use [UpdateMode::OnDemand] and only call
+    /// [WindowHelper::request_redraw] when something has changed.
     #[allow(unused_variables)]
     #[inline]
     fn on_draw(
@@ -217,6 +239,23 @@ pub trait WindowHandler<UserEventType = ()>
     {
     }
 
+    /// Invoked with the contents of the window once a frame requested using
+    /// [WindowHelper::capture_frame] has been completely drawn.
+    ///
+    /// The data is in [crate::image::ImageDataType::RGBA] format, with rows
+    /// ordered from top to bottom, and its size is the window's size in
+    /// physical pixels. Its alpha is handled as requested, see
+    /// [WindowHelper::capture_frame_with_alpha].
+    #[allow(unused_variables)]
+    #[inline]
+    fn on_frame_captured(
+        &mut self,
+        helper: &mut WindowHelper<UserEventType>,
+        frame: RawBitmapData
+    )
+    {
+    }
+
     /// Invoked when the mouse changes position.
     ///
     /// Normally, this provides the absolute  position of the mouse in the
@@ -270,6 +309,12 @@ pub trait WindowHandler<UserEventType = ()>
 
     /// Invoked when a keyboard key is pressed.
     ///
+    /// The backend doesn't report raw scancodes, so `scancode` is always
+    /// [UNKNOWN_SCANCODE]. To bind keys by their physical position (e.g. WASD
+    /// on an AZERTY keyboard), use `virtual_key_code`: on Windows, macOS and
+    /// the web it identifies the physical key, named after its position on a
+    /// US keyboard. On Linux it follows the active keyboard layout instead.
+    ///
     /// To detect when a character is typed, see the
     /// [WindowHandler::on_keyboard_char] callback.
     #[allow(unused_variables)]
@@ -283,7 +328,8 @@ pub trait WindowHandler<UserEventType = ()>
     {
     }
 
-    /// Invoked when a keyboard key is released.
+    /// Invoked when a keyboard key is released. See
+    /// [WindowHandler::on_key_down] regarding `scancode`.
     #[allow(unused_variables)]
     #[inline]
     fn on_key_up(
@@ -299,6 +345,11 @@ pub trait WindowHandler<UserEventType = ()>
     ///
     /// This is invoked in addition to the [WindowHandler::on_key_up] and
     /// [WindowHandler::on_key_down] callbacks.
+    ///
+    /// Text composed with an input method editor (IME) is delivered here one
+    /// character at a time once it is committed. The text being composed is
+    /// shown by the IME itself, and isn't reported to the application. See
+    /// [WindowHelper::set_ime_position].
     #[allow(unused_variables)]
     #[inline]
     fn on_keyboard_char(
@@ -414,6 +465,8 @@ where
         scale_factor: f64
     )
     {
+        self.renderer.borrow_mut().clear_font_caches();
+
         self.window_handler
             .on_scale_factor_changed(helper, scale_factor)
     }
@@ -424,7 +477,16 @@ where
         let renderer = &mut self.renderer;
         let window_handler = &mut self.window_handler;
 
-        renderer.borrow_mut().draw_frame(|graphics| window_handler.on_draw(helper, graphics))
+        if let Some(alpha) = helper.inner().take_frame_capture_request() {
+            renderer.borrow_mut().request_frame_capture(alpha);
+        }
+
+        renderer.borrow_mut().draw_frame(|graphics| window_handler.on_draw(helper, graphics));
+
+        let captured_frame = renderer.borrow_mut().take_captured_frame();
+        if let Some(frame) = captured_frame {
+            window_handler.on_frame_captured(helper, frame);
+        }
     }
 
     #[inline]
@@ -645,6 +707,14 @@ impl<UserEventType> WindowHelper<UserEventType>
         self.inner.get_size_pixels()
     }
 
+    /// Gets the window size in physical pixels, without dividing by the
+    /// window's scale factor. This is the size of the default framebuffer, and
+    /// is the size to use for operations such as reading back pixels.
+    pub fn get_size_physical_pixels(&self) -> UVec2
+    {
+        self.inner.get_size_physical_pixels()
+    }
+
     /// Sets the position of the window in pixels. If multiple monitors are in
     /// use, this will be the distance from the top left of the display
     /// area, spanning all the monitors.
@@ -674,7 +744,133 @@ impl<UserEventType> WindowHelper<UserEventType>
         self.inner.set_position_scaled_pixels(position)
     }
 
-    /// Gets the window's scale factor.
+    /// Sets when [WindowHandler::on_update] and [WindowHandler::on_draw] are
+    /// invoked. The default is [UpdateMode::Continuous].
+    ///
+    /// In [UpdateMode::OnDemand] mode, events sent through a
+    /// [UserEventSender] are only delivered on the next frame, so the sending
+    /// thread can't wake up an idle window.
+    pub fn set_update_mode(&self, mode: UpdateMode)
+    {
+        self.inner.set_update_mode(mode)
+    }
+
+    /// Invokes [WindowHandler::on_update] at a fixed rate of
+    /// `updates_per_second`, independent of the frame rate, so that each
+    /// update can advance the simulation by the same constant step. Passing
+    /// `None` goes back to one update per frame.
+    ///
+    /// Updates are driven by an accumulator of real elapsed time. When
+    /// drawing, use [WindowHelper::get_fixed_update_alpha] to interpolate
+    /// between the previous and current simulation states. To keep a slow
+    /// frame from stalling the app, a single frame runs a limited number of
+    /// updates, and any time left over beyond that is dropped.
+    ///
+    /// The rate must be finite and greater than zero. Any other rate is
+    /// ignored, leaving the previous setting in place.
+    pub fn set_fixed_update(&self, updates_per_second: Option<f64>)
+    {
+        self.inner.set_fixed_update(updates_per_second)
+    }
+
+    /// The time step between fixed updates in seconds, or `None` if no fixed
+    /// update rate is set.
+    #[inline]
+    #[must_use]
+    pub fn get_fixed_update_delta(&self) -> Option<f64>
+    {
+        self.inner.get_fixed_update_delta()
+    }
+
+    /// How far the current frame lies between the last fixed update and the
+    /// next one, from `0.0` to `1.0`. Always `0.0` if no fixed update rate is
+    /// set.
+    #[inline]
+    #[must_use]
+    pub fn get_fixed_update_alpha(&self) -> f64
+    {
+        self.inner.get_fixed_update_alpha()
+    }
+
+    /// The number of frames drawn so far. This is `0` during the first call to
+    /// [WindowHandler::on_draw], and increases by one after each call.
+    #[inline]
+    #[must_use]
+    pub fn frame_number(&self) -> u64
+    {
+        self.inner.frame_number()
+    }
+
+    /// Takes a screenshot of the next frame once it has been completely drawn,
+    /// and passes it to [WindowHandler::on_frame_captured].
+    ///
+    /// The captured pixels are fully opaque. Only supported with the OpenGL
+    /// backend. With Metal, no frame is captured.
+    pub fn capture_frame(&self)
+    {
+        self.inner.capture_frame(CaptureAlpha::Opaque)
+    }
+
+    /// Takes a screenshot like [WindowHelper::capture_frame], treating the
+    /// alpha channel as given by `alpha`.
+    ///
+    /// [CaptureAlpha::Preserve] returns whatever alpha was left in the
+    /// window's framebuffer. That is only meaningful if every pixel of the
+    /// frame was drawn with the alpha wanted in the capture, including the
+    /// clear color.
+    pub fn capture_frame_with_alpha(&self, alpha: CaptureAlpha)
+    {
+        self.inner.capture_frame(alpha)
+    }
+
+    /// Returns true if the specified key is currently held down.
+    ///
+    /// This reflects the [WindowHandler::on_key_down] and
+    /// [WindowHandler::on_key_up] events received so far, and is useful for
+    /// continuous actions such as movement in [WindowHandler::on_update].
+    #[inline]
+    #[must_use]
+    pub fn is_key_down(&self, code: miniquad::KeyCode) -> bool
+    {
+        self.inner.is_key_down(code)
+    }
+
+    /// Returns true if the specified mouse button is currently held down.
+    ///
+    /// This reflects the [WindowHandler::on_mouse_button_down] and
+    /// [WindowHandler::on_mouse_button_up] events received so far, and is
+    /// useful for drag interactions in [WindowHandler::on_update].
+    #[inline]
+    #[must_use]
+    pub fn is_mouse_button_down(&self, button: MouseButton) -> bool
+    {
+        self.inner.is_mouse_button_down(button)
+    }
+
+    /// Moves the input method editor (IME) candidate window to the specified
+    /// position in scaled device-independent pixels, relative to the top left
+    /// of the window. Call this when the text cursor moves, so that the
+    /// candidate window stays next to the text being typed.
+    ///
+    /// On Android, this function has no effect.
+    pub fn set_ime_position<P: Into<Vec2>>(&self, position: P)
+    {
+        self.inner.set_ime_position(position.into())
+    }
+
+    /// Enables or disables the input method editor (IME). Enable it while a
+    /// text field has focus, and disable it otherwise so that key presses
+    /// (e.g. WASD movement) reach the application directly.
+    ///
+    /// On Android, this function has no effect.
+    pub fn set_ime_enabled(&self, enabled: bool)
+    {
+        self.inner.set_ime_enabled(enabled)
+    }
+
+    /// Gets the window's scale factor, as reported by the operating system.
+    /// This doesn't include the content scale (see
+    /// [WindowHelper::set_content_scale]).
     #[inline]
     #[must_use]
     pub fn get_scale_factor(&self) -> f64
@@ -682,6 +878,30 @@ impl<UserEventType> WindowHelper<UserEventType>
         self.inner.get_scale_factor()
     }
 
+    /// Sets a zoom factor for everything drawn in the window, on top of the
+    /// scale factor of the display. For example, a content scale of `1.5`
+    /// makes text and shapes half as large again, which is useful as an
+    /// accessibility setting. The default is `1.0`.
+    ///
+    /// Positions and sizes, including those of mouse events and the window
+    /// size, are in pixels scaled by both factors. Text is rasterized at the
+    /// combined scale, so it stays sharp. After the scale changes,
+    /// [WindowHandler::on_resize] is invoked with the new scaled size.
+    ///
+    /// Panics if `scale` isn't a positive, finite number.
+    pub fn set_content_scale(&self, scale: f32)
+    {
+        self.inner.set_content_scale(scale)
+    }
+
+    /// Gets the content scale. See [WindowHelper::set_content_scale].
+    #[inline]
+    #[must_use]
+    pub fn get_content_scale(&self) -> f32
+    {
+        self.inner.get_content_scale()
+    }
+
     /// Creates a [UserEventSender], which can be used to post custom events to
     /// this event loop from another thread.
     ///
@@ -862,9 +1082,15 @@ pub enum WindowSize
     ScaledPixels(Vec2),
     /// Make the window fill the screen, except for a margin around the outer
     /// edges.
+    ///
+    /// The screen size isn't known before the window is created, so this
+    /// currently creates a window of miniquad's default size, 800x600 pixels.
     MarginPhysicalPixels(u32),
     /// Make the window fill the screen, except for a margin around the outer
     /// edges.
+    ///
+    /// The screen size isn't known before the window is created, so this
+    /// currently creates a window of miniquad's default size, 800x600 pixels.
     MarginScaledPixels(f32)
 }
 
@@ -876,9 +1102,26 @@ pub enum WindowPosition
     Center,
     /// Place the window at the specified pixel location from the top left of
     /// the primary monitor.
+    ///
+    /// Negative coordinates aren't supported, and leave the window wherever
+    /// the platform places it.
     PrimaryMonitorPixelsFromTopLeft(IVec2)
 }
 
+/// Controls when the event loop runs [WindowHandler::on_update] and
+/// [WindowHandler::on_draw]. See [WindowHelper::set_update_mode].
+#[derive(Debug, Hash, PartialEq, Eq, Clone, Copy)]
+pub enum UpdateMode
+{
+    /// Update and draw every frame, paced by the swap interval. Suited to
+    /// games and animations. This is the default.
+    Continuous,
+    /// Only update and draw after input events, window resizes, or a call to
+    /// [WindowHelper::request_redraw]. The event loop sleeps in between,
+    /// which suits tools and other GUIs that are mostly idle.
+    OnDemand
+}
+
 /// Whether or not the window is in fullscreen mode.
 #[derive(Debug, Hash, PartialEq, Eq, Clone, Copy)]
 pub enum WindowFullscreenMode
@@ -895,12 +1138,14 @@ pub struct WindowCreationOptions
 {
     pub(crate) mode: WindowCreationMode,
     pub(crate) multisampling: u16,
-    pub(crate) vsync: bool,
+    pub(crate) swap_interval: u32,
     pub(crate) always_on_top: bool,
     pub(crate) resizable: bool,
     pub(crate) maximized: bool,
     pub(crate) transparent: bool,
-    pub(crate) decorations: bool
+    pub(crate) decorations: bool,
+    pub(crate) srgb: bool,
+    pub(crate) high_dpi: bool
 }
 
 impl WindowCreationOptions
@@ -928,12 +1173,14 @@ impl WindowCreationOptions
         WindowCreationOptions {
             mode,
             multisampling: 16,
-            vsync: true,
+            swap_interval: 1,
             always_on_top: false,
             resizable: true,
             maximized: false,
             decorations: true,
-            transparent: false
+            transparent: false,
+            srgb: false,
+            high_dpi: true
         }
     }
 
@@ -959,7 +1206,27 @@ impl WindowCreationOptions
     #[must_use]
     pub fn with_vsync(mut self, vsync: bool) -> Self
     {
-        self.vsync = vsync;
+        self.swap_interval = if vsync { 1 } else { 0 };
+        self
+    }
+
+    /// Sets the number of display refreshes to wait for between frames. `0`
+    /// disables vsync, leaving the frame rate uncapped, `1` (the default)
+    /// presents one frame per refresh, and `2` halves the frame rate, e.g. to
+    /// 30fps on a 60Hz display to save power.
+    ///
+    /// [WindowHandler::on_update] and [WindowHandler::on_draw] are invoked
+    /// once per frame, so this paces them too. [WindowHelper::request_redraw]
+    /// doesn't cause any additional frames.
+    ///
+    /// This is only a hint to the graphics driver, which may ignore it. It
+    /// is also ignored for `WebCanvas`, where the browser decides the frame
+    /// rate.
+    #[inline]
+    #[must_use]
+    pub fn with_swap_interval(mut self, swap_interval: u32) -> Self
+    {
+        self.swap_interval = swap_interval;
         self
     }
 
@@ -975,6 +1242,8 @@ impl WindowCreationOptions
 
     /// If set to `true`, the window will be placed above other windows. The
     /// default is `false`.
+    ///
+    /// This is currently ignored, as the windowing backend doesn't support it.
     #[inline]
     #[must_use]
     pub fn with_always_on_top(mut self, always_on_top: bool) -> Self
@@ -985,6 +1254,8 @@ impl WindowCreationOptions
 
     /// If set to `true`, the window will be initially maximized. The default is
     /// `false`.
+    ///
+    /// This is currently ignored, as the windowing backend doesn't support it.
     #[inline]
     #[must_use]
     pub fn with_maximized(mut self, maximized: bool) -> Self
@@ -995,6 +1266,8 @@ impl WindowCreationOptions
 
     /// If set to `false`, the window will have no border.  The default is
     /// `true`.
+    ///
+    /// This is currently ignored, as the windowing backend doesn't support it.
     #[inline]
     #[must_use]
     pub fn with_decorations(mut self, decorations: bool) -> Self
@@ -1003,6 +1276,22 @@ impl WindowCreationOptions
         self
     }
 
+    /// If set to `true`, colors are blended and interpolated in linear light,
+    /// which is physically correct, rather than directly in sRGB. See
+    /// [GLRenderer::set_srgb](crate::GLRenderer::set_srgb). The default is
+    /// `false`.
+    ///
+    /// Note that this depends on platform support. If the window's framebuffer
+    /// isn't sRGB capable, a warning is logged and colors are blended in sRGB
+    /// as usual.
+    #[inline]
+    #[must_use]
+    pub fn with_srgb(mut self, srgb: bool) -> Self
+    {
+        self.srgb = srgb;
+        self
+    }
+
     /// Sets whether the background of the window should be transparent. The
     /// default is `false`.
     ///
@@ -1015,7 +1304,114 @@ impl WindowCreationOptions
         self.transparent = transparent;
         self
     }
+
+    /// If set to `true`, the window is drawn at the full resolution of high
+    /// DPI displays, and logical pixels are scaled up to match. Otherwise the
+    /// window is rendered at a lower resolution and stretched by the system.
+    /// The default is `true`.
+    #[inline]
+    #[must_use]
+    pub fn with_high_dpi(mut self, high_dpi: bool) -> Self
+    {
+        self.high_dpi = high_dpi;
+        self
+    }
+
+    /// The miniquad configuration for a window with these options.
+    ///
+    /// The scale factor isn't known until the window exists, so a size in
+    /// scaled pixels is used as it is. Sizes given as a margin need the
+    /// screen size, which also isn't known, so the default size is used.
+    pub(crate) fn to_conf(&self, title: &str) -> miniquad::conf::Conf
+    {
+        let defaults = miniquad::conf::Conf::default();
+
+        let (window_width, window_height) = match &self.mode {
+            WindowCreationMode::Windowed {
+                size: WindowSize::PhysicalPixels(size),
+                ..
+            } => (size.x as i32, size.y as i32),
+            WindowCreationMode::Windowed {
+                size: WindowSize::ScaledPixels(size),
+                ..
+            } => (size.x.round() as i32, size.y.round() as i32),
+            _ => (defaults.window_width, defaults.window_height)
+        };
+
+        miniquad::conf::Conf {
+            window_title: title.to_string(),
+            window_width,
+            window_height,
+            high_dpi: self.high_dpi,
+            fullscreen: self.mode == WindowCreationMode::FullscreenBorderless,
+            sample_count: self.multisampling.max(1) as i32,
+            window_resizable: self.resizable,
+            platform: miniquad::conf::Platform {
+                swap_interval: Some(self.swap_interval as i32),
+                // frames are scheduled by Stage, according to the update mode
+                blocking_event_loop: true,
+                framebuffer_alpha: self.transparent,
+                ..Default::default()
+            },
+            ..defaults
+        }
+    }
+
+    /// Where to move the window to once it has been created, if anywhere.
+    /// miniquad only takes unsigned positions, so a position left of or above
+    /// the monitor is skipped rather than clamped.
+    pub(crate) fn initial_position(&self) -> Option<UVec2>
+    {
+        match &self.mode {
+            WindowCreationMode::Windowed {
+                position: Some(WindowPosition::PrimaryMonitorPixelsFromTopLeft(position)),
+                ..
+            } if position.x >= 0 && position.y >= 0 => {
+                Some(UVec2::new(position.x as u32, position.y as u32))
+            }
+            _ => None
+        }
+    }
 }
 
 /// Type representing a keyboard scancode.
 pub type KeyScancode = u32;
+
+/// The scancode reported for every key, as the backend doesn't provide
+/// scancodes. See [WindowHandler::on_key_down].
+pub const UNKNOWN_SCANCODE: KeyScancode = 0;
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+
+    #[test]
+    fn test_to_conf()
+    {
+        let options = WindowCreationOptions::new_windowed(
+            WindowSize::PhysicalPixels(UVec2::new(640, 480)),
+            Some(WindowPosition::PrimaryMonitorPixelsFromTopLeft(IVec2::new(10, 20)))
+        )
+        .with_resizable(false)
+        .with_multisampling(4)
+        .with_high_dpi(false);
+
+        let conf = options.to_conf("Title");
+        assert_eq!(conf.window_title, "Title");
+        assert_eq!((conf.window_width, conf.window_height), (640, 480));
+        assert!(!conf.fullscreen && !conf.window_resizable && !conf.high_dpi);
+        assert_eq!(conf.sample_count, 4);
+        assert_eq!(options.initial_position(), Some(UVec2::new(10, 20)));
+
+        let options = WindowCreationOptions::new_windowed(
+            WindowSize::PhysicalPixels(UVec2::new(640, 480)),
+            Some(WindowPosition::PrimaryMonitorPixelsFromTopLeft(IVec2::new(-10, 20)))
+        );
+        assert_eq!(options.initial_position(), None);
+
+        let conf = WindowCreationOptions::new_fullscreen_borderless().to_conf("");
+        assert!(conf.fullscreen && conf.high_dpi);
+        assert_eq!(conf.platform.swap_interval, Some(1));
+    }
+}
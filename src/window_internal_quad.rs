@@ -16,18 +16,23 @@
 
 use std::cell::Cell;
 use std::cell::RefCell;
+use std::collections::HashSet;
 use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc::{Sender, Receiver};
 use std::sync::mpsc;
 
 use crate::dimen::{IVec2, UVec2, Vec2, Vector2};
+use crate::dpi::{self, DpiScaler};
 use crate::error::{BacktraceError, ErrorMessage};
+use crate::image::CaptureAlpha;
 use crate::window::{
     DrawingWindowHandler,
     EventLoopSendError,
     ModifiersState,
     MouseButton,
     MouseScrollDistance,
+    UpdateMode,
     UserEventSender,
     WindowCreationError,
     WindowCreationMode,
@@ -38,19 +43,42 @@ use crate::window::{
     WindowHelper,
     WindowPosition,
     WindowSize,
-    WindowStartupInfo
+    WindowStartupInfo,
+    UNKNOWN_SCANCODE
 };
 use crate::GLRenderer;
 use crate::Color;
 
+/// miniquad keeps its display in a process-wide global, which can only be set
+/// once, so only the first call to `run_loop` may start an event loop.
+static WINDOW_STARTED: AtomicBool = AtomicBool::new(false);
+
+/// The time step for a fixed update rate, or `None` unless the rate is finite
+/// and above zero.
+fn fixed_update_delta(updates_per_second: f64) -> Option<f64>
+{
+    if updates_per_second.is_finite() && updates_per_second > 0.0 {
+        Some(1.0 / updates_per_second)
+    } else {
+        None
+    }
+}
+
 pub(crate) struct WindowHelperQuad<UserEventType: 'static>
 {
     renderer: Rc<RefCell<GLRenderer>>,
     event_proxy: Sender<UserEventType>,
     redraw_requested: Cell<bool>,
+    frame_capture_requested: Cell<Option<CaptureAlpha>>,
+    update_mode: Cell<UpdateMode>,
+    fixed_update_delta: Cell<Option<f64>>,
+    fixed_update_alpha: Cell<f64>,
+    frame_number: u64,
     terminate_requested: bool,
     physical_size: UVec2,
     is_mouse_grabbed: Cell<bool>,
+    keys_down: HashSet<miniquad::KeyCode>,
+    mouse_buttons_down: HashSet<MouseButton>,
     tmp: std::marker::PhantomData<UserEventType>
 }
 
@@ -67,9 +95,16 @@ impl<UserEventType> WindowHelperQuad<UserEventType>
             renderer: renderer,
             event_proxy: ep,
             redraw_requested: Cell::new(false),
+            frame_capture_requested: Cell::new(None),
+            update_mode: Cell::new(UpdateMode::Continuous),
+            fixed_update_delta: Cell::new(None),
+            fixed_update_alpha: Cell::new(0.0),
+            frame_number: 0,
             terminate_requested: false,
             physical_size: initial_physical_size,
             is_mouse_grabbed: Cell::new(false),
+            keys_down: HashSet::new(),
+            mouse_buttons_down: HashSet::new(),
             tmp: std::marker::PhantomData {},
         }
     }
@@ -93,6 +128,85 @@ impl<UserEventType> WindowHelperQuad<UserEventType>
         self.redraw_requested.set(redraw_requested);
     }
 
+    pub fn capture_frame(&self, alpha: CaptureAlpha)
+    {
+        self.frame_capture_requested.set(Some(alpha));
+        self.request_redraw();
+    }
+
+    #[inline]
+    pub fn take_frame_capture_request(&self) -> Option<CaptureAlpha>
+    {
+        self.frame_capture_requested.replace(None)
+    }
+
+    pub fn set_update_mode(&self, mode: UpdateMode)
+    {
+        self.update_mode.set(mode);
+
+        if mode == UpdateMode::Continuous {
+            miniquad::window::schedule_update();
+        }
+    }
+
+    pub fn set_fixed_update(&self, updates_per_second: Option<f64>)
+    {
+        let delta = match updates_per_second {
+            None => None,
+            Some(rate) => match fixed_update_delta(rate) {
+                Some(delta) => Some(delta),
+                None => {
+                    log::warn!("Ignoring invalid fixed update rate {}", rate);
+                    return;
+                }
+            }
+        };
+
+        self.fixed_update_delta.set(delta);
+        self.fixed_update_alpha.set(0.0);
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn get_fixed_update_delta(&self) -> Option<f64>
+    {
+        self.fixed_update_delta.get()
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn get_fixed_update_alpha(&self) -> f64
+    {
+        self.fixed_update_alpha.get()
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn frame_number(&self) -> u64
+    {
+        self.frame_number
+    }
+
+    pub fn increment_frame_number(&mut self)
+    {
+        self.frame_number += 1;
+    }
+
+    #[inline]
+    pub fn set_fixed_update_alpha(&self, alpha: f64)
+    {
+        self.fixed_update_alpha.set(alpha);
+    }
+
+    /// Whether the event loop should run another frame straight after the
+    /// current one, rather than waiting for an event.
+    #[inline]
+    #[must_use]
+    pub fn is_next_frame_scheduled(&self) -> bool
+    {
+        self.update_mode.get() == UpdateMode::Continuous || self.redraw_requested.get()
+    }
+
     #[inline]
     pub fn get_event_loop_action(&self) -> WindowEventLoopAction
     {
@@ -160,8 +274,13 @@ impl<UserEventType> WindowHelperQuad<UserEventType>
     pub fn get_size_pixels(&self) -> UVec2
     {
         let (w, h) = miniquad::window::screen_size();
-        let dpi = miniquad::window::dpi_scale();
-        return UVec2::new((w / dpi) as u32, (h / dpi) as u32);
+        DpiScaler::current().to_logical_size(w, h)
+    }
+
+    pub fn get_size_physical_pixels(&self) -> UVec2
+    {
+        let (w, h) = miniquad::window::screen_size();
+        UVec2::new(w as u32, h as u32)
     }
 
     pub fn set_size_scaled_pixels<S: Into<Vec2>>(&self, size: S)
@@ -176,6 +295,49 @@ impl<UserEventType> WindowHelperQuad<UserEventType>
     {
     }
 
+    #[inline]
+    #[must_use]
+    pub fn is_key_down(&self, code: miniquad::KeyCode) -> bool
+    {
+        self.keys_down.contains(&code)
+    }
+
+    pub fn set_key_down(&mut self, code: miniquad::KeyCode, down: bool)
+    {
+        if down {
+            self.keys_down.insert(code);
+        } else {
+            self.keys_down.remove(&code);
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn is_mouse_button_down(&self, button: MouseButton) -> bool
+    {
+        self.mouse_buttons_down.contains(&button)
+    }
+
+    pub fn set_mouse_button_down(&mut self, button: MouseButton, down: bool)
+    {
+        if down {
+            self.mouse_buttons_down.insert(button);
+        } else {
+            self.mouse_buttons_down.remove(&button);
+        }
+    }
+
+    pub fn set_ime_position(&self, position: Vec2)
+    {
+        let (x, y) = DpiScaler::current().to_physical_point(position);
+        miniquad::window::set_ime_position(x, y);
+    }
+
+    pub fn set_ime_enabled(&self, enabled: bool)
+    {
+        miniquad::window::set_ime_enabled(enabled);
+    }
+
     #[inline]
     #[must_use]
     pub fn get_scale_factor(&self) -> f64
@@ -183,6 +345,25 @@ impl<UserEventType> WindowHelperQuad<UserEventType>
         miniquad::window::dpi_scale().into()
     }
 
+    pub fn set_content_scale(&self, scale: f32)
+    {
+        assert!(
+            scale.is_finite() && scale > 0.0,
+            "Content scale must be positive, got {}",
+            scale
+        );
+
+        dpi::set_content_scale(scale);
+        self.request_redraw();
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn get_content_scale(&self) -> f32
+    {
+        dpi::content_scale()
+    }
+
     pub fn create_user_event_sender(&self) -> UserEventSender<UserEventType>
     {
         UserEventSender::new(UserEventSenderQuad::new(self.event_proxy.clone()))
@@ -222,31 +403,50 @@ impl<UserEventType: 'static> WindowQuad<UserEventType>
         return UVec2::new(w as u32, h as u32);
     }
 
-    pub fn run_loop<Handler>(self, handler: Handler)
+    pub fn run_loop<Handler>(self, handler: Handler) -> Result<(), BacktraceError<WindowCreationError>>
     where
         Handler: WindowHandler<UserEventType> + 'static
     {
-        // TODO get initial width and height
-        let config = 
-            miniquad::conf::Conf {
-                window_width: 1200,
-                window_height: 1200,
-                window_title: self.title.to_string(),
-                high_dpi: true,
-                ..Default::default()
-            };
+        if WINDOW_STARTED.swap(true, Ordering::SeqCst) {
+            return Err(BacktraceError::new(
+                WindowCreationError::WindowAlreadyRunning
+            ));
+        }
+
+        let config = self.options.to_conf(&self.title);
+
+        let srgb = self.options.srgb;
+        let initial_position = self.options.initial_position();
+        let renderer_error = Rc::new(RefCell::new(None));
+        let renderer_error_inner = renderer_error.clone();
 
         miniquad::start(miniquad::conf::Conf { ..config }, move || {
             let (tx, rx): (Sender<UserEventType>, Receiver<UserEventType>) = mpsc::channel();
+            if let Some(position) = initial_position {
+                miniquad::window::set_window_position(position.x, position.y);
+            }
             let (w, h) = miniquad::window::screen_size();
             let initial_viewport_size_pixels = UVec2::new(w as u32, h as u32);
-            let dpi = miniquad::window::dpi_scale();
-
-            let renderer = GLRenderer::new_for_quad();
+            let dpi = DpiScaler::current();
+
+            let mut renderer = match GLRenderer::new_for_quad() {
+                Ok(renderer) => renderer,
+                Err(err) => {
+                    log::error!("{:?}", err);
+                    *renderer_error_inner.borrow_mut() = Some(err);
+                    miniquad::window::order_quit();
+                    return Box::new(ClosingStage);
+                }
+            };
+            if srgb {
+                if let Err(err) = renderer.set_srgb(true) {
+                    log::warn!("Falling back to non-sRGB rendering: {:?}", err);
+                }
+            }
             let renderer = RefCell::new(renderer);
             let renderer = Rc::new(renderer);
 
-            let scaled_size = UVec2::new((w / dpi) as u32, (h / dpi) as u32);
+            let scaled_size = dpi.to_logical_size(w, h);
             let mut helper = WindowHelper::new(WindowHelperQuad::new(
                 initial_viewport_size_pixels, // TODO is this right?
                 tx.clone(),
@@ -258,14 +458,26 @@ impl<UserEventType: 'static> WindowQuad<UserEventType>
                 &mut helper,
                 WindowStartupInfo::new(
                     scaled_size,
-                    miniquad::window::dpi_scale().into(),
+                    dpi.scale().into(),
                 )
             );
 
+            // the event loop blocks until the first frame is scheduled
+            miniquad::window::schedule_update();
+
             Box::new(Stage::new(handler, helper, rx))
         });
 
         //panic!("reached end of the event loop?"); // TODO should not get here
+
+        let renderer_error = renderer_error.borrow_mut().take();
+        match renderer_error {
+            Some(err) => Err(BacktraceError::new_with_cause(
+                WindowCreationError::RendererCreationFailed,
+                err
+            )),
+            None => Ok(())
+        }
     }
 
 }
@@ -329,6 +541,18 @@ impl<UserEventType> UserEventSenderQuad<UserEventType>
     }
 }
 
+/// Stands in for the [Stage] while the window closes after the renderer could
+/// not be created.
+struct ClosingStage;
+
+impl miniquad::EventHandler for ClosingStage {
+    fn update(&mut self) {
+    }
+
+    fn draw(&mut self) {
+    }
+}
+
 struct Stage<UserEventType, HandlerType>
     where UserEventType: 'static,
         HandlerType: WindowHandler<UserEventType> + 'static
@@ -336,12 +560,24 @@ struct Stage<UserEventType, HandlerType>
     handler: DrawingWindowHandler<UserEventType, HandlerType>,
     helper: WindowHelper<UserEventType>,
     user_events: Receiver<UserEventType>,
+    scale_factor: f32,
+    content_scale: f32,
+    last_update_time: f64,
+    fixed_update_accumulator: f64,
 }
 
 impl<UserEventType, HandlerType: WindowHandler<UserEventType>> Stage<UserEventType, HandlerType>
 {
     const DEFAULT_BG_COLOR: Color = Color::BLACK;
 
+    // Longest stretch of time fixed updates will try to catch up on in one
+    // frame, so a long stall doesn't cause ever more updates per frame.
+    const MAX_FIXED_UPDATE_CATCH_UP_SECS: f64 = 0.25;
+
+    // Most fixed updates run in one frame, for rates so high that even the
+    // capped catch up time would take too many.
+    const MAX_FIXED_UPDATES_PER_FRAME: u32 = 240;
+
     fn new(
         handler: DrawingWindowHandler<UserEventType, HandlerType>,
         helper: WindowHelper<UserEventType>,
@@ -353,15 +589,84 @@ impl<UserEventType, HandlerType: WindowHandler<UserEventType>> Stage<UserEventTy
             handler: handler,
             helper: helper,
             user_events: user_events,
+            scale_factor: miniquad::window::dpi_scale(),
+            content_scale: dpi::content_scale(),
+            last_update_time: miniquad::date::now(),
+            fixed_update_accumulator: 0.0,
         }
     }
 
+    fn run_updates(&mut self)
+    {
+        let now = miniquad::date::now();
+        let elapsed = (now - self.last_update_time).min(Self::MAX_FIXED_UPDATE_CATCH_UP_SECS);
+        self.last_update_time = now;
+
+        let delta = match self.helper.inner().get_fixed_update_delta() {
+            Some(delta) => delta,
+            None => {
+                self.fixed_update_accumulator = 0.0;
+                self.handler.on_update(&mut self.helper);
+                return;
+            }
+        };
+
+        self.fixed_update_accumulator += elapsed;
+        let mut updates = 0;
+        while self.fixed_update_accumulator >= delta {
+            if updates == Self::MAX_FIXED_UPDATES_PER_FRAME {
+                // too far behind to catch up, drop the rest
+                self.fixed_update_accumulator %= delta;
+                break;
+            }
+
+            self.fixed_update_accumulator -= delta;
+            self.handler.on_update(&mut self.helper);
+            updates += 1;
+        }
+
+        self.helper
+            .inner()
+            .set_fixed_update_alpha(self.fixed_update_accumulator / delta);
+    }
+
+    // The event loop blocks until a frame is scheduled. Input always gets a
+    // frame, so that on-demand handlers can react to it.
+    fn schedule_frame(&self)
+    {
+        miniquad::window::schedule_update();
+    }
+
+    // miniquad has no event for DPI changes, so poll for them instead. Glyphs are
+    // cached per DPI-scaled size, so text is rasterized at the new scale on its
+    // next draw, and the glyphs cached at the old scale are discarded.
+    fn check_scale_factor(&mut self)
+    {
+        let scale_factor = miniquad::window::dpi_scale();
+        if scale_factor != self.scale_factor {
+            self.scale_factor = scale_factor;
+            self.handler.on_scale_factor_changed(&mut self.helper, scale_factor.into());
+        }
+
+        // the window's logical size depends on the content scale
+        let content_scale = dpi::content_scale();
+        if content_scale != self.content_scale {
+            self.content_scale = content_scale;
+            self.helper.inner().renderer.borrow_mut().clear_font_caches();
+            let (width, height) = miniquad::window::screen_size();
+            let size = DpiScaler::current().to_logical_size(width, height);
+            self.handler.on_resize(&mut self.helper, size);
+        }
+    }
 }
 
 impl<UserEventType, HandlerType: WindowHandler<UserEventType>> miniquad::EventHandler for Stage<UserEventType, HandlerType> {
     fn resize_event(&mut self, width: f32, height: f32) {
-        let dpi = miniquad::window::dpi_scale();
-        self.handler.on_resize(&mut self.helper, UVec2::new((width / dpi) as u32, (height / dpi) as u32));
+        self.schedule_frame();
+        self.check_scale_factor();
+
+        let size = DpiScaler::current().to_logical_size(width, height);
+        self.handler.on_resize(&mut self.helper, size);
     }
 
     fn raw_mouse_motion(&mut self, x: f32, y: f32) {
@@ -369,21 +674,29 @@ impl<UserEventType, HandlerType: WindowHandler<UserEventType>> miniquad::EventHa
     }
 
     fn mouse_motion_event(&mut self, x: f32, y: f32) {
-        let dpi = miniquad::window::dpi_scale();
-        self.handler.on_mouse_move(&mut self.helper, Vec2::new(x / dpi, y / dpi));
+        self.schedule_frame();
+        let position = DpiScaler::current().to_logical_point(x, y);
+        self.handler.on_mouse_move(&mut self.helper, position);
     }
 
     fn mouse_wheel_event(&mut self, x: f32, y: f32) {
+        self.schedule_frame();
     }
 
     fn mouse_button_down_event(&mut self, btn: MouseButton, x: f32, y: f32) {
-        let dpi = miniquad::window::dpi_scale();
-        self.handler.on_mouse_button_down(&mut self.helper, btn, x / dpi, y / dpi);
+        self.schedule_frame();
+        self.helper.inner().set_mouse_button_down(btn, true);
+
+        let position = DpiScaler::current().to_logical_point(x, y);
+        self.handler.on_mouse_button_down(&mut self.helper, btn, position.x, position.y);
     }
 
     fn mouse_button_up_event(&mut self, btn: MouseButton, x: f32, y: f32) {
-        let dpi = miniquad::window::dpi_scale();
-        self.handler.on_mouse_button_up(&mut self.helper, btn, x / dpi, y / dpi);
+        self.schedule_frame();
+        self.helper.inner().set_mouse_button_down(btn, false);
+
+        let position = DpiScaler::current().to_logical_point(x, y);
+        self.handler.on_mouse_button_up(&mut self.helper, btn, position.x, position.y);
     }
 
 /*
@@ -392,20 +705,30 @@ impl<UserEventType, HandlerType: WindowHandler<UserEventType>> miniquad::EventHa
 */
 
     fn char_event(&mut self, character: char, modifiers: miniquad::KeyMods, repeat: bool) {
+        self.schedule_frame();
         self.handler.on_keyboard_char(&mut self.helper, character);
     }
 
     fn key_down_event(&mut self, keycode: miniquad::KeyCode, modifiers: miniquad::KeyMods, repeat: bool) {
+        self.schedule_frame();
+        self.helper.inner().set_key_down(keycode, true);
+
         // TODO why is the keycode in the window handler an option?
-        self.handler.on_key_down(&mut self.helper, Some(keycode), 0); // TODO
+        self.handler.on_key_down(&mut self.helper, Some(keycode), UNKNOWN_SCANCODE);
     }
 
     fn key_up_event(&mut self, keycode: miniquad::KeyCode, modifiers: miniquad::KeyMods) {
+        self.schedule_frame();
+        self.helper.inner().set_key_down(keycode, false);
+
+        self.handler.on_key_up(&mut self.helper, Some(keycode), UNKNOWN_SCANCODE);
     }
 
     fn update(&mut self) {
+        self.check_scale_factor();
+
         self.helper.inner().set_redraw_requested(false);
-        self.handler.on_update(&mut self.helper);
+        self.run_updates();
         match self.user_events.try_recv()
         {
             Ok(x) => 
@@ -420,6 +743,11 @@ impl<UserEventType, HandlerType: WindowHandler<UserEventType>> miniquad::EventHa
 
     fn draw(&mut self) {
         self.handler.on_draw(&mut self.helper);
+        self.helper.inner().increment_frame_number();
+
+        if self.helper.inner().is_next_frame_scheduled() {
+            self.schedule_frame();
+        }
     }
 
     fn window_restored_event(&mut self) {
@@ -432,3 +760,19 @@ impl<UserEventType, HandlerType: WindowHandler<UserEventType>> miniquad::EventHa
     }
 }
 
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+
+    #[test]
+    fn test_fixed_update_delta()
+    {
+        assert_eq!(fixed_update_delta(50.0), Some(0.02));
+
+        for &rate in &[0.0, -60.0, f64::INFINITY, f64::NEG_INFINITY, f64::NAN] {
+            assert_eq!(fixed_update_delta(rate), None, "rate {}", rate);
+        }
+    }
+}